@@ -0,0 +1,391 @@
+use std::cmp;
+use std::io;
+use std::time::Duration;
+
+use ::{SerialDevice,ModemStatus,ModemLines,ClearBuffer,TimeoutMode};
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+
+/// A serial device with buffered reads and writes.
+///
+/// `BufferedPort` combines the roles of `BufReader` and `BufWriter` for a serial device, while
+/// still implementing `SerialDevice`, so the control signals, timeouts, and settings of the
+/// device remain available through the [`SerialPort`](trait.SerialPort.html) interface.
+///
+/// Buffered output is written to the device before every read from the device, so a request is
+/// always sent before waiting for its response. It's also written before the settings are
+/// changed, before draining, and when the `BufferedPort` is dropped.
+///
+/// Timeouts don't lose data. A read that times out leaves the read buffer as it was, and if a
+/// write times out while the buffered output is being written to the device, the bytes that
+/// weren't written remain buffered.
+///
+/// ```no_run
+/// use std::io::prelude::*;
+///
+/// let port = serial::open("COM1").unwrap();
+/// let mut port = serial::BufferedPort::new(port);
+///
+/// port.write_all(b"AT\r\n").unwrap();
+///
+/// let mut line = String::new();
+/// port.read_line(&mut line).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct BufferedPort<D: SerialDevice> {
+    // only None after into_inner() has taken the device
+    device: Option<D>,
+    read_buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    write_buf: Vec<u8>
+}
+
+impl<D: SerialDevice> BufferedPort<D> {
+    /// Creates a `BufferedPort` with read and write buffers of 1KB each.
+    pub fn new(device: D) -> Self {
+        BufferedPort::with_capacity(DEFAULT_CAPACITY, DEFAULT_CAPACITY, device)
+    }
+
+    /// Creates a `BufferedPort` with read and write buffers of the given sizes in bytes.
+    pub fn with_capacity(read_capacity: usize, write_capacity: usize, device: D) -> Self {
+        BufferedPort {
+            device: Some(device),
+            read_buf: vec![0; read_capacity].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            write_buf: Vec::with_capacity(write_capacity)
+        }
+    }
+
+    /// Returns a reference to the underlying device.
+    pub fn get_ref(&self) -> &D {
+        self.device.as_ref().unwrap()
+    }
+
+    /// Returns a mutable reference to the underlying device.
+    ///
+    /// Reading from or writing to the device directly bypasses the buffers.
+    pub fn get_mut(&mut self) -> &mut D {
+        self.device.as_mut().unwrap()
+    }
+
+    /// Returns the bytes that have been received but not yet read.
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.read_buf[self.pos..self.cap]
+    }
+
+    /// Returns the bytes that have been written but not yet sent to the device.
+    pub fn write_buffer(&self) -> &[u8] {
+        &self.write_buf
+    }
+
+    /// Writes the buffered output to the device and unwraps it.
+    ///
+    /// Any buffered input is discarded.
+    ///
+    /// ## Errors
+    ///
+    /// If the buffered output can't be written, the error is returned and the `BufferedPort` is
+    /// dropped, which discards the output that remains buffered.
+    pub fn into_inner(mut self) -> io::Result<D> {
+        try!(self.flush_buf());
+        Ok(self.device.take().unwrap())
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        let mut written = 0;
+        let mut result = Ok(());
+
+        while written < self.write_buf.len() {
+            match self.device.as_mut().unwrap().write(&self.write_buf[written..]) {
+                Ok(0) => {
+                    result = Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write buffered data"));
+                    break;
+                },
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        // keep whatever wasn't written so a timed out write can be retried
+        self.write_buf.drain(..written);
+        result
+    }
+}
+
+impl<D: SerialDevice> Drop for BufferedPort<D> {
+    fn drop(&mut self) {
+        if self.device.is_some() {
+            let _ = self.flush_buf();
+        }
+    }
+}
+
+impl<D: SerialDevice> io::Read for BufferedPort<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // bypass the read buffer for reads that are at least as large as it
+        if self.pos == self.cap && buf.len() >= self.read_buf.len() {
+            try!(self.flush_buf());
+            return self.get_mut().read(buf);
+        }
+
+        let len = {
+            let available = try!(io::BufRead::fill_buf(self));
+            let len = cmp::min(available.len(), buf.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            len
+        };
+
+        io::BufRead::consume(self, len);
+        Ok(len)
+    }
+}
+
+impl<D: SerialDevice> io::BufRead for BufferedPort<D> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.cap {
+            try!(self.flush_buf());
+
+            // a failed read leaves the buffer empty, so no received bytes are lost
+            self.cap = try!(self.device.as_mut().unwrap().read(&mut self.read_buf));
+            self.pos = 0;
+        }
+
+        Ok(&self.read_buf[self.pos..self.cap])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.cap);
+    }
+}
+
+impl<D: SerialDevice> io::Write for BufferedPort<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.write_buf.len() + buf.len() > self.write_buf.capacity() {
+            try!(self.flush_buf());
+        }
+
+        if buf.len() >= self.write_buf.capacity() {
+            self.get_mut().write(buf)
+        }
+        else {
+            self.write_buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        try!(self.flush_buf());
+        self.get_mut().flush()
+    }
+}
+
+impl<D: SerialDevice> SerialDevice for BufferedPort<D> {
+    type Settings = D::Settings;
+
+    fn read_settings(&self) -> ::Result<D::Settings> {
+        self.get_ref().read_settings()
+    }
+
+    fn write_settings(&mut self, settings: &D::Settings) -> ::Result<()> {
+        // output that was written before the settings changed is sent with the old settings
+        try!(self.flush_buf());
+        self.get_mut().write_settings(settings)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.get_ref().timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        self.get_mut().set_timeout(timeout)
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.get_ref().write_timeout()
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        self.get_mut().set_write_timeout(timeout)
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.get_ref().inter_byte_timeout()
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        self.get_mut().set_inter_byte_timeout(timeout)
+    }
+
+    fn timeout_mode(&self) -> TimeoutMode {
+        self.get_ref().timeout_mode()
+    }
+
+    fn set_timeout_mode(&mut self, mode: TimeoutMode) -> ::Result<()> {
+        self.get_mut().set_timeout_mode(mode)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        self.get_mut().set_nonblocking(nonblocking)
+    }
+
+    fn is_exclusive(&self) -> bool {
+        self.get_ref().is_exclusive()
+    }
+
+    fn set_exclusive(&mut self, exclusive: bool) -> ::Result<()> {
+        self.get_mut().set_exclusive(exclusive)
+    }
+
+    fn set_low_latency(&mut self, low_latency: bool) -> ::Result<()> {
+        self.get_mut().set_low_latency(low_latency)
+    }
+
+    fn set_hangup_on_close(&mut self, hangup: bool) -> ::Result<()> {
+        self.get_mut().set_hangup_on_close(hangup)
+    }
+
+    fn drain(&mut self) -> ::Result<()> {
+        try!(self.flush_buf());
+        self.get_mut().drain()
+    }
+
+    fn clear(&mut self, buffer: ClearBuffer) -> ::Result<()> {
+        match buffer {
+            ClearBuffer::Input => self.pos = self.cap,
+            ClearBuffer::Output => self.write_buf.clear(),
+            ClearBuffer::All => {
+                self.pos = self.cap;
+                self.write_buf.clear();
+            }
+        }
+
+        self.get_mut().clear(buffer)
+    }
+
+    fn wait_readable(&mut self, timeout: Duration) -> ::Result<bool> {
+        if self.pos < self.cap {
+            return Ok(true);
+        }
+
+        // a request that is still buffered can't be answered
+        try!(self.flush_buf());
+        self.get_mut().wait_readable(timeout)
+    }
+
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        let buffered = self.cap - self.pos;
+        Ok(buffered + try!(self.get_mut().bytes_available()))
+    }
+
+    fn set_rts(&mut self, level: bool) -> ::Result<()> {
+        self.get_mut().set_rts(level)
+    }
+
+    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
+        self.get_mut().set_dtr(level)
+    }
+
+    fn set_break(&mut self) -> ::Result<()> {
+        self.get_mut().set_break()
+    }
+
+    fn clear_break(&mut self) -> ::Result<()> {
+        self.get_mut().clear_break()
+    }
+
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        self.get_mut().modem_status()
+    }
+
+    fn wait_modem_change(&mut self, lines: ModemLines) -> ::Result<ModemStatus> {
+        self.get_mut().wait_modem_change(lines)
+    }
+
+    fn read_cts(&mut self) -> ::Result<bool> {
+        self.get_mut().read_cts()
+    }
+
+    fn read_dsr(&mut self) -> ::Result<bool> {
+        self.get_mut().read_dsr()
+    }
+
+    fn read_ri(&mut self) -> ::Result<bool> {
+        self.get_mut().read_ri()
+    }
+
+    fn read_cd(&mut self) -> ::Result<bool> {
+        self.get_mut().read_cd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::io::prelude::*;
+    use std::time::Duration;
+
+    use super::*;
+    use ::SerialPort;
+    use ::loopback::LoopbackPort;
+
+    #[test]
+    fn buffered_port_holds_writes_until_flushed() {
+        let mut port = BufferedPort::new(LoopbackPort::new());
+        port.write_all(b"abc").unwrap();
+        assert_eq!(port.write_buffer(), b"abc");
+
+        port.flush().unwrap();
+        assert_eq!(port.write_buffer(), b"");
+    }
+
+    #[test]
+    fn buffered_port_sends_buffered_output_before_reading() {
+        let mut port = BufferedPort::new(LoopbackPort::new());
+        port.write_all(b"line\n").unwrap();
+
+        let mut line = String::new();
+        port.read_line(&mut line).unwrap();
+        assert_eq!(line, "line\n");
+    }
+
+    #[test]
+    fn buffered_port_keeps_buffered_input_after_timeout() {
+        let mut port = BufferedPort::with_capacity(16, 16, LoopbackPort::new());
+        SerialPort::set_timeout(&mut port, Duration::from_millis(1)).unwrap();
+        port.write_all(b"abcd").unwrap();
+
+        let mut buf = [0u8; 2];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(port.read_buffer(), b"cd");
+
+        let mut rest = [0u8; 4];
+        assert_eq!(port.read(&mut rest).unwrap(), 2);
+        assert_eq!(port.read(&mut rest).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn buffered_port_counts_buffered_and_pending_input() {
+        let mut port = BufferedPort::with_capacity(16, 16, LoopbackPort::new());
+        port.write_all(b"abcd").unwrap();
+
+        let mut buf = [0u8; 1];
+        port.read_exact(&mut buf).unwrap();
+        port.get_mut().write_all(b"xy").unwrap();
+
+        assert_eq!(SerialPort::bytes_available(&mut port).unwrap(), 5);
+    }
+
+    #[test]
+    fn buffered_port_forwards_control_signals() {
+        let mut port = BufferedPort::new(LoopbackPort::new());
+        SerialPort::set_rts(&mut port, true).unwrap();
+        assert!(port.get_ref().rts());
+    }
+}
@@ -1,9 +1,20 @@
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
+#[cfg(all(windows, feature = "async"))]
+extern crate tokio;
+
+use std::cmp;
 use std::default::Default;
 use std::error::Error as StdError;
 use std::ffi::OsStr;
 use std::fmt;
 use std::io;
-use std::time::Duration;
+use std::ops;
+use std::str;
+use std::thread;
+use std::time::{Duration,Instant};
 
 
 pub use BaudRate::*;
@@ -11,6 +22,8 @@ pub use CharSize::*;
 pub use Parity::*;
 pub use StopBits::*;
 pub use FlowControl::*;
+pub use buffered::BufferedPort;
+pub use port::Port;
 
 /// A module that exports traits that are useful to have in scope.
 ///
@@ -24,6 +37,11 @@ pub mod prelude {
 }
 
 
+pub mod loopback;
+
+mod buffered;
+mod port;
+
 #[cfg(windows)]
 pub mod windows;
 
@@ -40,10 +58,24 @@ pub type Result<T> = std::result::Result<T,::Error>;
 pub enum ErrorKind {
     /// The device is not available.
     ///
-    /// This could indicate that the device is in use by another process or was disconnected while
-    /// performing I/O.
+    /// This indicates that the device doesn't exist when opening it.
     NoDevice,
 
+    /// The device was disconnected while the port was open.
+    ///
+    /// This is reported when, e.g., a USB serial adapter is unplugged. The port can't be used
+    /// anymore, but the device may be opened again once it has been reconnected.
+    Disconnected,
+
+    /// The device is in use by another process.
+    Busy,
+
+    /// The process doesn't have permission to access the device.
+    ///
+    /// On Windows, opening a COM port that is already open in another process also fails with
+    /// this error, because Windows reports it in the same way as a lack of permission.
+    PermissionDenied,
+
     /// A parameter was incorrect.
     InvalidInput,
 
@@ -110,14 +142,64 @@ impl From<Error> for io::Error {
     fn from(error: Error) -> io::Error {
         let kind = match error.kind {
             ErrorKind::NoDevice => io::ErrorKind::NotFound,
+            ErrorKind::Disconnected => io::ErrorKind::NotConnected,
+            ErrorKind::Busy => io::ErrorKind::Other,
+            ErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
             ErrorKind::InvalidInput => io::ErrorKind::InvalidInput,
             ErrorKind::Io(kind) => kind
         };
 
-        io::Error::new(kind, error.description)
+        // the error is kept as the payload, so that `is_disconnected()` can find its kind
+        io::Error::new(kind, error)
+    }
+}
+
+/// Returns `true` if an I/O error indicates that the device was disconnected.
+///
+/// Reads and writes on a port whose device has been removed, e.g., an unplugged USB serial
+/// adapter, fail with an OS error code that the driver uses to report the removal, such as
+/// `ERROR_DEVICE_NOT_CONNECTED` on Windows or `ENXIO` on Unix. A `serial::Error` of kind
+/// `Disconnected` that was converted to an `io::Error` is recognized as well. This distinguishes a
+/// disconnected device from errors that may be transient, so that the port can be reopened.
+///
+/// This function is meant for errors returned by I/O on a port that is already open. Some of the
+/// error codes, e.g., `ERROR_ACCESS_DENIED` on Windows, have a different meaning for other
+/// operations.
+///
+/// ```no_run
+/// use std::io::prelude::*;
+///
+/// let mut port = serial::open("COM3").unwrap();
+/// let mut buf = [0u8; 64];
+///
+/// match port.read(&mut buf) {
+///     Ok(n) => println!("{:?}", &buf[..n]),
+///     Err(ref e) if serial::is_disconnected(e) => println!("device was unplugged"),
+///     Err(e) => println!("error: {}", e)
+/// }
+/// ```
+pub fn is_disconnected(err: &io::Error) -> bool {
+    if let Some(error) = err.get_ref().and_then(|inner| inner.downcast_ref::<Error>()) {
+        return error.kind() == ErrorKind::Disconnected;
+    }
+
+    match err.raw_os_error() {
+        Some(errno) => is_disconnect_error(errno),
+        None => false
     }
 }
 
+#[cfg(unix)]
+fn is_disconnect_error(errno: i32) -> bool {
+    extern crate libc;
+    errno == libc::ENXIO || errno == libc::ENODEV
+}
+
+#[cfg(windows)]
+fn is_disconnect_error(errno: i32) -> bool {
+    windows::is_disconnect_error(errno)
+}
+
 /// A convenience type alias for the system's native serial port type.
 #[cfg(unix)]
 pub type SystemPort = posix::TTYPort;
@@ -136,8 +218,9 @@ pub type SystemPort = windows::COMPort;
 ///
 /// This function returns an error if the device could not be opened and initialized:
 ///
-/// * `NoDevice` if the device could not be opened. This could indicate that the device is
-///   already in use.
+/// * `NoDevice` if the device does not exist.
+/// * `Busy` if the device is already in use.
+/// * `PermissionDenied` if the process isn't allowed to open the device.
 /// * `InvalidInput` if `port` is not a valid device name.
 /// * `Io` for any other error while opening or initializing the device.
 ///
@@ -175,8 +258,9 @@ pub fn open<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<SystemPort> {
 ///
 /// This function returns an error if the device could not be opened and initialized:
 ///
-/// * `NoDevice` if the device could not be opened. This could indicate that the device is
-///   already in use.
+/// * `NoDevice` if the device does not exist.
+/// * `Busy` if the device is already in use.
+/// * `PermissionDenied` if the process isn't allowed to open the device.
 /// * `InvalidInput` if `port` is not a valid device name.
 /// * `Io` for any other error while opening or initializing the device.
 ///
@@ -203,6 +287,268 @@ pub fn open<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<SystemPort> {
     windows::COMPort::open(port)
 }
 
+/// Checks whether a native serial port exists, without opening it.
+///
+/// Opening a port can have side effects, such as asserting DTR, which resets many boards. This
+/// function only looks the name up, so it's safe for probing. On Unix systems, the port exists if
+/// the path names a character device. On Windows, it exists if the system currently defines a
+/// device with that name.
+///
+/// A port that exists may still fail to open, e.g., because it's in use.
+///
+/// ```no_run
+/// if serial::port_exists("/dev/ttyUSB0") {
+///     let port = serial::open("/dev/ttyUSB0").unwrap();
+/// }
+/// ```
+#[cfg(unix)]
+pub fn port_exists<T: AsRef<OsStr> + ?Sized>(port: &T) -> bool {
+    use std::fs;
+    use std::os::unix::fs::FileTypeExt;
+
+    match fs::metadata(port.as_ref()) {
+        Ok(metadata) => metadata.file_type().is_char_device(),
+        Err(_) => false
+    }
+}
+
+/// Checks whether a native serial port exists, without opening it.
+///
+/// Opening a port can have side effects, such as asserting DTR, which resets many boards. This
+/// function only looks the name up, so it's safe for probing. On Unix systems, the port exists if
+/// the path names a character device. On Windows, it exists if the system currently defines a
+/// device with that name.
+///
+/// A port that exists may still fail to open, e.g., because it's in use.
+///
+/// ```no_run
+/// if serial::port_exists("COM3") {
+///     let port = serial::open("COM3").unwrap();
+/// }
+/// ```
+#[cfg(windows)]
+pub fn port_exists<T: AsRef<OsStr> + ?Sized>(port: &T) -> bool {
+    windows::port_exists(port.as_ref())
+}
+
+/// Opens a native serial port, retrying while the device is busy.
+///
+/// Another process sometimes holds a port briefly, e.g., a modem manager probing a device that
+/// was just plugged in. This function makes up to `attempts` attempts to open the port and waits
+/// for `backoff` after each one that fails with `Busy`. Other errors, such as `NoDevice`, are
+/// returned immediately. At least one attempt is made, even if `attempts` is zero.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let port = serial::open_retry("COM3", 5, Duration::from_millis(200)).unwrap();
+/// ```
+///
+/// ## Errors
+///
+/// The error from the last attempt is returned if the port couldn't be opened:
+///
+/// * `NoDevice` if the device does not exist.
+/// * `Busy` if the device was still in use after the last attempt.
+/// * `PermissionDenied` if the process isn't allowed to open the device.
+/// * `InvalidInput` if `port` is not a valid device name.
+/// * `Io` for any other error while opening or initializing the device.
+pub fn open_retry<T: AsRef<OsStr> + ?Sized>(port: &T, attempts: u32, backoff: Duration) -> ::Result<SystemPort> {
+    retry_busy(attempts, backoff, || ::open(port))
+}
+
+fn retry_busy<T, F: FnMut() -> ::Result<T>>(attempts: u32, backoff: Duration, mut open: F) -> ::Result<T> {
+    let mut attempt = 1;
+
+    loop {
+        match open() {
+            Err(ref e) if e.kind() == ErrorKind::Busy && attempt < attempts => {
+                thread::sleep(backoff);
+                attempt += 1;
+            },
+            result => return result
+        }
+    }
+}
+
+/// Information about a serial port that is present on the system.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct PortInfo {
+    /// The name of the port.
+    ///
+    /// The name can be passed directly to [`serial::open()`](fn.open.html).
+    pub port_name: String,
+
+    /// The USB vendor ID of the device that provides the port, if known.
+    pub vid: Option<u16>,
+
+    /// The USB product ID of the device that provides the port, if known.
+    pub pid: Option<u16>,
+
+    /// The kind of hardware that provides the port.
+    pub port_type: SerialPortType
+}
+
+/// The kind of hardware that provides a serial port.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum SerialPortType {
+    /// The port is provided by a USB device, such as a USB serial adapter.
+    UsbPort(UsbPortInfo),
+
+    /// The port is provided by a PCI or PCI Express card, or a UART on the motherboard.
+    PciPort,
+
+    /// The port is a Bluetooth serial port (SPP).
+    BluetoothPort,
+
+    /// The kind of hardware couldn't be determined, e.g., for a virtual port.
+    Unknown
+}
+
+/// Information about the USB device that provides a serial port.
+///
+/// The strings are included when the operating system reports them.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct UsbPortInfo {
+    /// The USB vendor ID.
+    pub vid: u16,
+
+    /// The USB product ID.
+    pub pid: u16,
+
+    /// The serial number of the device.
+    pub serial_number: Option<String>,
+
+    /// The manufacturer of the device.
+    pub manufacturer: Option<String>,
+
+    /// A description of the device.
+    pub product: Option<String>
+}
+
+/// Lists the serial ports that are present on the system.
+///
+/// On Windows, the returned port names are the names of COM ports, e.g., `COM3`. USB vendor and
+/// product IDs are included for ports that are provided by USB devices.
+///
+/// ## Errors
+///
+/// This function returns an error if the system's device list could not be read:
+///
+/// * `Io` for any error while enumerating devices.
+///
+/// ## Example
+///
+/// ```no_run
+/// for port in serial::available_ports().unwrap() {
+///     println!("{} {:?}:{:?}", port.port_name, port.vid, port.pid);
+/// }
+/// ```
+#[cfg(windows)]
+pub fn available_ports() -> ::Result<Vec<PortInfo>> {
+    windows::available_ports()
+}
+
+/// Returns an iterator over the serial ports that are present on the system.
+///
+/// The ports are the same as those listed by [`available_ports()`](fn.available_ports.html), but
+/// they're discovered lazily as the iterator advances, without collecting them into a `Vec`. This
+/// is useful for polling for a device, since enumeration can stop once it has been found.
+///
+/// ## Errors
+///
+/// The iterator yields an error if the system's device list could not be read:
+///
+/// * `Io` for any error while enumerating devices.
+///
+/// ## Example
+///
+/// ```no_run
+/// let found = serial::ports()
+///     .filter_map(|port| port.ok())
+///     .any(|port| port.port_name == "COM3");
+/// ```
+#[cfg(windows)]
+pub fn ports() -> windows::Ports {
+    windows::ports()
+}
+
+/// Finds the serial port that is provided by a USB device.
+///
+/// The port is matched by the USB vendor and product IDs of the device that provides it, which
+/// don't change when the operating system assigns the port a different name. If more than one
+/// port matches, the first one that was enumerated is returned.
+///
+/// ## Errors
+///
+/// This function returns an error if the system's device list could not be read:
+///
+/// * `Io` for any error while enumerating devices.
+///
+/// ## Example
+///
+/// ```no_run
+/// // FTDI FT232R
+/// if let Some(info) = serial::find_port(0x0403, 0x6001).unwrap() {
+///     let port = serial::open(&info.port_name).unwrap();
+/// }
+/// ```
+#[cfg(windows)]
+pub fn find_port(vid: u16, pid: u16) -> ::Result<Option<PortInfo>> {
+    find_usb_port(ports(), vid, pid)
+}
+
+/// Resets a board into its bootloader with a 1200 baud "touch".
+///
+/// Boards with native USB, such as the Arduino Leonardo, enter their bootloader when their port is
+/// opened at 1200 baud and closed again. This function opens the port, sets the baud rate to 1200,
+/// deasserts DTR, waits briefly, and closes the port. The board usually disappears while it
+/// restarts and reappears under a new name, so errors caused by the device being disconnected
+/// after the port has been opened are not reported.
+///
+/// ```no_run
+/// serial::touch_1200bps("COM4").unwrap();
+/// ```
+///
+/// ## Errors
+///
+/// * `NoDevice` if the device does not exist.
+/// * `Busy` if the device is already in use.
+/// * `PermissionDenied` if the process isn't allowed to open the device.
+/// * `InvalidInput` if `port` is not a valid device name.
+/// * `Io` for any other error while opening or configuring the device.
+pub fn touch_1200bps<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<()> {
+    let mut device = try!(::open(port));
+    touch_1200bps_device(&mut device)
+}
+
+fn touch_1200bps_device<T: SerialPort>(port: &mut T) -> ::Result<()> {
+    let result = port.reconfigure(&|settings| settings.set_baud_rate(Baud1200))
+        .and_then(|()| port.set_dtr(false));
+
+    match result {
+        Ok(()) => {
+            thread::sleep(Duration::from_millis(50));
+            Ok(())
+        },
+        Err(ref e) if e.kind() == ErrorKind::NoDevice || e.kind() == ErrorKind::Disconnected => Ok(()),
+        Err(e) => Err(e)
+    }
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn find_usb_port<I: IntoIterator<Item = ::Result<PortInfo>>>(ports: I, vid: u16, pid: u16) -> ::Result<Option<PortInfo>> {
+    for port in ports {
+        let port = try!(port);
+
+        if port.vid == Some(vid) && port.pid == Some(pid) {
+            return Ok(Some(port));
+        }
+    }
+
+    Ok(None)
+}
+
 
 /// Serial port baud rates.
 ///
@@ -296,10 +642,82 @@ impl BaudRate {
             BaudRate::BaudOther(n) => n,
         }
     }
+
+    /// Returns the time it takes to transmit `n` characters of `bits_per_char` bits each.
+    ///
+    /// `bits_per_char` is the length of the whole frame, including the start bit, parity bit, and
+    /// stop bits, e.g., 10 bits for 8N1 or 11 bits for 8E1. The duration of a single character
+    /// with a port's settings is also available from
+    /// [`COMSettings::frame_duration()`](windows/struct.COMSettings.html#method.frame_duration).
+    /// Returns zero for a baud rate of zero.
+    ///
+    /// ## Example
+    ///
+    /// The silent interval of Modbus RTU is 3.5 character times, i.e., half of 7 characters:
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use serial::BaudRate;
+    /// let silence = BaudRate::Baud19200.duration_for_chars(7, 11) / 2;
+    /// assert_eq!(silence, Duration::new(0, 2_005_208));
+    /// ```
+    pub fn duration_for_chars(&self, n: usize, bits_per_char: u32) -> Duration {
+        let baud = self.speed() as u64;
+
+        if baud == 0 {
+            return Duration::new(0, 0);
+        }
+
+        // split into whole seconds first so that long transfers don't overflow, and widen the
+        // remainder, which overflows a u64 when it's scaled to nanoseconds for very high baud rates
+        let bits = (n as u64).saturating_mul(bits_per_char as u64);
+        let nanos = (bits % baud) as u128 * 1_000_000_000 / baud as u128;
+
+        Duration::new(bits / baud, nanos as u32)
+    }
+}
+
+/// Baud rates are serialized as their numeric speed, e.g., `9600`, so that both standard and
+/// non-standard baud rates are written the same way.
+#[cfg(feature = "serde")]
+impl serde::Serialize for BaudRate {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.speed() as u64)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BaudRate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<BaudRate, D::Error> {
+        struct SpeedVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for SpeedVisitor {
+            type Value = BaudRate;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str("a baud rate as a positive integer")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, speed: u64) -> std::result::Result<BaudRate, E> {
+                Ok(BaudRate::from_speed(speed as usize))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, speed: i64) -> std::result::Result<BaudRate, E> {
+                if speed < 0 {
+                    return Err(E::invalid_value(serde::de::Unexpected::Signed(speed), &self));
+                }
+
+                Ok(BaudRate::from_speed(speed as usize))
+            }
+        }
+
+        deserializer.deserialize_u64(SpeedVisitor)
+    }
 }
 
 /// Number of bits per character.
 #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub enum CharSize {
     /** 5 bits per character. */ Bits5,
     /** 6 bits per character. */ Bits6,
@@ -307,6 +725,51 @@ pub enum CharSize {
     /** 8 bits per character. */ Bits8
 }
 
+impl CharSize {
+    /// Creates a `CharSize` from a number of bits per character.
+    ///
+    /// Character sizes other than 5 through 8 bits can't be represented. Nine-bit characters can
+    /// be emulated with `ParityMark` and `ParitySpace`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if `bits` is not between 5 and 8.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use serial::CharSize;
+    /// assert_eq!(CharSize::Bits7, CharSize::from_bits(7).unwrap());
+    /// assert!(CharSize::from_bits(4).is_err());
+    /// ```
+    pub fn from_bits(bits: u8) -> ::Result<CharSize> {
+        match bits {
+            5 => Ok(CharSize::Bits5),
+            6 => Ok(CharSize::Bits6),
+            7 => Ok(CharSize::Bits7),
+            8 => Ok(CharSize::Bits8),
+            _ => Err(::Error::new(0, ::ErrorKind::InvalidInput, format!("unsupported character size: {} bits", bits)))
+        }
+    }
+
+    /// Returns the number of bits per character.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use serial::CharSize;
+    /// assert_eq!(8, CharSize::Bits8.bits());
+    /// ```
+    pub fn bits(&self) -> u8 {
+        match *self {
+            CharSize::Bits5 => 5,
+            CharSize::Bits6 => 6,
+            CharSize::Bits7 => 7,
+            CharSize::Bits8 => 8
+        }
+    }
+}
+
 /// Parity checking modes.
 ///
 /// When parity checking is enabled (`ParityOdd` or `ParityEven`) an extra bit is transmitted with
@@ -314,9 +777,14 @@ pub enum CharSize {
 /// character (including the parity bit) is an even number (`ParityEven`) or an odd number
 /// (`ParityOdd`).
 ///
+/// The parity bit can also be held at a constant value regardless of the character: always 1
+/// (`ParityMark`) or always 0 (`ParitySpace`). This is commonly used to emulate 9-bit addressing on
+/// multidrop buses.
+///
 /// Parity checking is disabled by setting `ParityNone`, in which case parity bits are not
 /// transmitted.
 #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub enum Parity {
     /// No parity bit.
     ParityNone,
@@ -325,23 +793,36 @@ pub enum Parity {
     ParityOdd,
 
     /// Parity bit sets even number of 1 bits.
-    ParityEven
+    ParityEven,
+
+    /// Parity bit is always 1.
+    ParityMark,
+
+    /// Parity bit is always 0.
+    ParitySpace
 }
 
 /// Number of stop bits.
 ///
 /// Stop bits are transmitted after every character.
 #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub enum StopBits {
     /// One stop bit.
     Stop1,
 
+    /// One and a half stop bits.
+    ///
+    /// This is typically only supported in combination with 5-bit characters.
+    Stop1_5,
+
     /// Two stop bits.
     Stop2
 }
 
 /// Flow control modes.
 #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub enum FlowControl {
     /// No flow control.
     FlowNone,
@@ -353,77 +834,1152 @@ pub enum FlowControl {
     FlowHardware
 }
 
-/// A trait for implementing serial devices.
-///
-/// This trait is meant to be used to implement new serial port devices. To use a serial port
-/// device, the [`SerialPort`](trait.SerialPort.html) trait should be used instead. Any type that
-/// implements the `SerialDevice` trait will automatically implement the `SerialPort` trait as
-/// well.
-///
-/// To implement a new serial port device, it's necessary to define a type that can manipulate the
-/// serial port device's settings (baud rate, parity mode, etc). This type is defined by the
-/// `Settings` associated type. The current settings should be determined by reading from the
-/// hardware or operating system for every call to `read_settings()`. The settings can then be
-/// manipulated in memory before being commited to the device with `write_settings()`.
-///
-/// Types that implement `SerialDevice` must also implement `std::io::Read` and `std::io::Write`.
-/// The `read()` and `write()` operations of these traits should honor the timeout that has been
-/// set with the most recent successful call to `set_timeout()`. This timeout value should also be
-/// accessible by calling the `timeout()` method.
+fn parse_error(what: &str, s: &str, expected: &str) -> ::Error {
+    ::Error::new(0, ::ErrorKind::InvalidInput, format!("invalid {}: {:?} (expected {})", what, s, expected))
+}
+
+/// Baud rates are displayed as their numeric speed, e.g., `9600`.
+impl fmt::Display for BaudRate {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.speed())
+    }
+}
+
+/// Parses a baud rate from an integer speed, e.g., `"115200"`.
 ///
-/// A serial port device should also provide access to some basic control signals: RTS, DTR, CTS,
-/// DSR, RI, and CD. The values for the control signals are represented as boolean values, with
-/// `true` indicating the the control signal is active.
+/// Any positive integer is accepted. Standard speeds map to their named variants and other speeds
+/// map to `BaudOther`, the same as [`BaudRate::from_speed()`](enum.BaudRate.html#method.from_speed).
 ///
-/// Lastly, types that implement `SerialDevice` should release any acquired resources when dropped.
-pub trait SerialDevice: io::Read+io::Write {
-    /// A type that implements the settings for the serial port device.
-    ///
-    /// The `Settings` type is used to retrieve and modify the serial port's settings. This type
-    /// should own any native structures used to manipulate the device's settings, but it should
-    /// not cause any changes in the underlying hardware until written to the device with
-    /// `write_settings()`.
-    type Settings: SerialPortSettings;
+/// ```
+/// assert_eq!(serial::Baud115200, "115200".parse().unwrap());
+/// assert_eq!(serial::BaudOther(250000), "250000".parse().unwrap());
+/// assert!("fast".parse::<serial::BaudRate>().is_err());
+/// ```
+impl str::FromStr for BaudRate {
+    type Err = ::Error;
 
-    /// Returns the device's current settings.
-    ///
-    /// This function attempts to read the current settings from the hardware. The hardware's
-    /// current settings may not match the settings that were most recently written to the hardware
-    /// with `write_settings()`.
-    ///
-    /// ## Errors
-    ///
-    /// This function returns an error if the settings could not be read from the underlying
-    /// hardware:
-    ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `Io` for any other type of I/O error.
-    fn read_settings(&self) -> ::Result<Self::Settings>;
+    fn from_str(s: &str) -> ::Result<BaudRate> {
+        match s.trim().parse::<usize>() {
+            Ok(speed) if speed > 0 => Ok(BaudRate::from_speed(speed)),
+            _ => Err(parse_error("baud rate", s, "a positive integer"))
+        }
+    }
+}
 
-    /// Applies new settings to the serial device.
-    ///
-    /// This function attempts to apply all settings to the serial device. Some settings may not be
-    /// supported by the underlying hardware, in which case the result is dependent on the
-    /// implementation. A successful return value does not guarantee that all settings were
-    /// appliied successfully. To check which settings were applied by a successful write,
-    /// applications should use the `read_settings()` method to obtain the latest configuration
-    /// state from the device.
-    ///
-    /// ## Errors
-    ///
+/// Character sizes are displayed as the number of bits, e.g., `8`.
+impl fmt::Display for CharSize {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.bits())
+    }
+}
+
+/// Parses a character size from a number of bits, `"5"` through `"8"`.
+impl str::FromStr for CharSize {
+    type Err = ::Error;
+
+    fn from_str(s: &str) -> ::Result<CharSize> {
+        match s.trim() {
+            "5" => Ok(CharSize::Bits5),
+            "6" => Ok(CharSize::Bits6),
+            "7" => Ok(CharSize::Bits7),
+            "8" => Ok(CharSize::Bits8),
+            _ => Err(parse_error("character size", s, "5, 6, 7, or 8"))
+        }
+    }
+}
+
+/// Parity modes are displayed as `none`, `odd`, `even`, `mark`, or `space`.
+impl fmt::Display for Parity {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match *self {
+            Parity::ParityNone  => "none",
+            Parity::ParityOdd   => "odd",
+            Parity::ParityEven  => "even",
+            Parity::ParityMark  => "mark",
+            Parity::ParitySpace => "space"
+        })
+    }
+}
+
+/// Parses a parity mode from the names written by `Display`, ignoring case.
+///
+/// ```
+/// assert_eq!(serial::ParityEven, "even".parse().unwrap());
+/// assert_eq!(serial::ParityNone, "None".parse().unwrap());
+/// assert!("sometimes".parse::<serial::Parity>().is_err());
+/// ```
+impl str::FromStr for Parity {
+    type Err = ::Error;
+
+    fn from_str(s: &str) -> ::Result<Parity> {
+        match &s.trim().to_ascii_lowercase()[..] {
+            "none"  => Ok(Parity::ParityNone),
+            "odd"   => Ok(Parity::ParityOdd),
+            "even"  => Ok(Parity::ParityEven),
+            "mark"  => Ok(Parity::ParityMark),
+            "space" => Ok(Parity::ParitySpace),
+            _ => Err(parse_error("parity", s, "none, odd, even, mark, or space"))
+        }
+    }
+}
+
+/// Stop bits are displayed as `1`, `1.5`, or `2`.
+impl fmt::Display for StopBits {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match *self {
+            StopBits::Stop1   => "1",
+            StopBits::Stop1_5 => "1.5",
+            StopBits::Stop2   => "2"
+        })
+    }
+}
+
+/// Parses a number of stop bits, `"1"`, `"1.5"`, or `"2"`.
+impl str::FromStr for StopBits {
+    type Err = ::Error;
+
+    fn from_str(s: &str) -> ::Result<StopBits> {
+        match s.trim() {
+            "1"   => Ok(StopBits::Stop1),
+            "1.5" => Ok(StopBits::Stop1_5),
+            "2"   => Ok(StopBits::Stop2),
+            _ => Err(parse_error("stop bits", s, "1, 1.5, or 2"))
+        }
+    }
+}
+
+/// Flow control modes are displayed as `none`, `software`, or `hardware`.
+impl fmt::Display for FlowControl {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match *self {
+            FlowControl::FlowNone     => "none",
+            FlowControl::FlowSoftware => "software",
+            FlowControl::FlowHardware => "hardware"
+        })
+    }
+}
+
+/// Parses a flow control mode from the names written by `Display`, ignoring case.
+impl str::FromStr for FlowControl {
+    type Err = ::Error;
+
+    fn from_str(s: &str) -> ::Result<FlowControl> {
+        match &s.trim().to_ascii_lowercase()[..] {
+            "none"     => Ok(FlowControl::FlowNone),
+            "software" => Ok(FlowControl::FlowSoftware),
+            "hardware" => Ok(FlowControl::FlowHardware),
+            _ => Err(parse_error("flow control", s, "none, software, or hardware"))
+        }
+    }
+}
+
+/// The state of a serial port's input control signals.
+///
+/// Each field is `true` if the corresponding control signal is asserted.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub struct ModemStatus {
+    /// CTS (Clear To Send) control signal.
+    pub cts: bool,
+
+    /// DSR (Data Set Ready) control signal.
+    pub dsr: bool,
+
+    /// RI (Ring Indicator) control signal.
+    pub ri: bool,
+
+    /// CD (Carrier Detect) control signal.
+    pub cd: bool
+}
+
+impl ModemStatus {
+    /// Returns the control signals that are asserted.
+    pub fn asserted(&self) -> ModemLines {
+        ModemLines { cts: self.cts, dsr: self.dsr, ri: self.ri, cd: self.cd }
+    }
+
+    /// Returns the control signals whose state differs from `previous`.
+    ///
+    /// This is useful after
+    /// [`SerialPort::wait_modem_change()`](trait.SerialPort.html#method.wait_modem_change) to
+    /// find out which of the watched signals changed.
+    pub fn changed(&self, previous: &ModemStatus) -> ModemLines {
+        ModemLines {
+            cts: self.cts != previous.cts,
+            dsr: self.dsr != previous.dsr,
+            ri: self.ri != previous.ri,
+            cd: self.cd != previous.cd
+        }
+    }
+}
+
+/// A selection of input control signals.
+///
+/// Lines are selected for
+/// [`SerialPort::wait_modem_change()`](trait.SerialPort.html#method.wait_modem_change) and
+/// reported by `ModemStatus::asserted()` and `ModemStatus::changed()`. Each field is `true` if the
+/// corresponding control signal is selected. Selections can be combined with `|` and intersected
+/// with `&`:
+///
+/// ```
+/// use serial::ModemLines;
+///
+/// let cts = ModemLines { cts: true, ..ModemLines::default() };
+/// let dsr = ModemLines { dsr: true, ..ModemLines::default() };
+///
+/// assert!((cts | dsr).contains(cts));
+/// assert!((cts & dsr).is_empty());
+/// ```
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub struct ModemLines {
+    /// CTS (Clear To Send) control signal.
+    pub cts: bool,
+
+    /// DSR (Data Set Ready) control signal.
+    pub dsr: bool,
+
+    /// RI (Ring Indicator) control signal.
+    pub ri: bool,
+
+    /// CD (Carrier Detect) control signal.
+    pub cd: bool
+}
+
+impl ModemLines {
+    /// Returns a selection of all input control signals.
+    pub fn all() -> Self {
+        ModemLines { cts: true, dsr: true, ri: true, cd: true }
+    }
+
+    /// Returns `true` if no control signals are selected.
+    pub fn is_empty(&self) -> bool {
+        !(self.cts || self.dsr || self.ri || self.cd)
+    }
+
+    /// Returns `true` if every control signal selected in `other` is also selected in `self`.
+    pub fn contains(&self, other: ModemLines) -> bool {
+        (*self & other) == other
+    }
+}
+
+impl ops::BitOr for ModemLines {
+    type Output = ModemLines;
+
+    fn bitor(self, other: ModemLines) -> ModemLines {
+        ModemLines {
+            cts: self.cts || other.cts,
+            dsr: self.dsr || other.dsr,
+            ri: self.ri || other.ri,
+            cd: self.cd || other.cd
+        }
+    }
+}
+
+impl ops::BitAnd for ModemLines {
+    type Output = ModemLines;
+
+    fn bitand(self, other: ModemLines) -> ModemLines {
+        ModemLines {
+            cts: self.cts && other.cts,
+            dsr: self.dsr && other.dsr,
+            ri: self.ri && other.ri,
+            cd: self.cd && other.cd
+        }
+    }
+}
+
+/// Buffers that can be cleared with [`SerialPort::clear()`](trait.SerialPort.html#method.clear).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ClearBuffer {
+    /// Data that has been received but not yet read.
+    Input,
+
+    /// Data that has been written but not yet transmitted.
+    Output,
+
+    /// Both input and output data.
+    All
+}
+
+/// Ways of applying the read timeout.
+///
+/// The timeout mode is selected with
+/// [`SerialPort::set_timeout_mode()`](trait.SerialPort.html#method.set_timeout_mode). It
+/// determines what the duration passed to `set_timeout()` measures.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum TimeoutMode {
+    /// The timeout is a deadline for the read as a whole.
+    ///
+    /// A read returns when the buffer is filled or the timeout expires, whichever happens first.
+    /// If no bytes were received before the timeout expired, the read returns an error of kind
+    /// `io::ErrorKind::TimedOut`. This is the default mode.
+    Total,
+
+    /// The timeout is the maximum idle time between two received bytes.
+    ///
+    /// A read waits indefinitely for the first byte. Once a byte has been received, the read
+    /// returns when the buffer is filled or the line has been idle for longer than the timeout.
+    /// A steady trickle of bytes keeps the read going.
+    InterByte,
+
+    /// Reads don't time out.
+    ///
+    /// A read blocks until the buffer has been filled.
+    None
+}
+
+// deriving Default for an enum needs Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for TimeoutMode {
+    fn default() -> Self {
+        TimeoutMode::Total
+    }
+}
+
+/// A step of a control signal sequence.
+///
+/// Sequences of steps are performed by
+/// [`SerialPort::reset_sequence()`](trait.SerialPort.html#method.reset_sequence).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum ResetStep {
+    /// Sets the level of the DTR control signal.
+    SetDtr(bool),
+
+    /// Sets the level of the RTS control signal.
+    SetRts(bool),
+
+    /// Waits for the given duration.
+    Delay(Duration),
+
+    /// Transmits a break condition for the given duration.
+    Break(Duration)
+}
+
+/// A trait for implementing serial devices.
+///
+/// This trait is meant to be used to implement new serial port devices. To use a serial port
+/// device, the [`SerialPort`](trait.SerialPort.html) trait should be used instead. Any type that
+/// implements the `SerialDevice` trait will automatically implement the `SerialPort` trait as
+/// well.
+///
+/// To implement a new serial port device, it's necessary to define a type that can manipulate the
+/// serial port device's settings (baud rate, parity mode, etc). This type is defined by the
+/// `Settings` associated type. The current settings should be determined by reading from the
+/// hardware or operating system for every call to `read_settings()`. The settings can then be
+/// manipulated in memory before being commited to the device with `write_settings()`.
+///
+/// Types that implement `SerialDevice` must also implement `std::io::Read` and `std::io::Write`.
+/// The `read()` operation should honor the timeout that has been set with the most recent
+/// successful call to `set_timeout()`, and the `write()` operation should honor the timeout that
+/// has been set with the most recent successful call to `set_write_timeout()`. These timeout values
+/// should also be accessible by calling the `timeout()` and `write_timeout()` methods.
+///
+/// A serial port device should also provide access to some basic control signals: RTS, DTR, CTS,
+/// DSR, RI, and CD. The values for the control signals are represented as boolean values, with
+/// `true` indicating the the control signal is active.
+///
+/// Lastly, types that implement `SerialDevice` should release any acquired resources when dropped.
+pub trait SerialDevice: io::Read+io::Write {
+    /// A type that implements the settings for the serial port device.
+    ///
+    /// The `Settings` type is used to retrieve and modify the serial port's settings. This type
+    /// should own any native structures used to manipulate the device's settings, but it should
+    /// not cause any changes in the underlying hardware until written to the device with
+    /// `write_settings()`.
+    type Settings: SerialPortSettings;
+
+    /// Returns the device's current settings.
+    ///
+    /// This function attempts to read the current settings from the hardware. The hardware's
+    /// current settings may not match the settings that were most recently written to the hardware
+    /// with `write_settings()`.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the settings could not be read from the underlying
+    /// hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn read_settings(&self) -> ::Result<Self::Settings>;
+
+    /// Applies new settings to the serial device.
+    ///
+    /// This function attempts to apply all settings to the serial device. Some settings may not be
+    /// supported by the underlying hardware, in which case the result is dependent on the
+    /// implementation. A successful return value does not guarantee that all settings were
+    /// appliied successfully. To check which settings were applied by a successful write,
+    /// applications should use the `read_settings()` method to obtain the latest configuration
+    /// state from the device.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the settings could not be applied to the underlying
+    /// hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
+    /// * `Io` for any other type of I/O error.
+    fn write_settings(&mut self, settings: &Self::Settings) -> ::Result<()>;
+
+    /// Returns the current timeout.
+    fn timeout(&self) -> Duration;
+
+    /// Sets the timeout for future I/O operations.
+    ///
+    /// By default, the timeout is a deadline for each read as a whole. Use `set_timeout_mode()`
+    /// to treat it as the maximum idle time between received bytes instead.
+    ///
+    /// A timeout of zero means that reads don't wait at all: a read returns the bytes that have
+    /// already been received, or an error of kind `io::ErrorKind::TimedOut` if there are none.
+    /// This differs from the Windows API, where a timeout of zero disables the timeout. Reads
+    /// that never time out are selected with `TimeoutMode::None` instead.
+    ///
+    /// Windows measures timeouts in whole milliseconds, so a timeout that isn't a whole number of
+    /// milliseconds is rounded up. A timeout shorter than a millisecond becomes one millisecond,
+    /// never zero.
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()>;
+
+    /// Returns the current write timeout.
+    ///
+    /// The default implementation returns zero, i.e., writes don't time out.
+    fn write_timeout(&self) -> Duration {
+        Duration::from_millis(0)
+    }
+
+    /// Sets the timeout for future write operations.
+    ///
+    /// A write that can not be completed before the timeout expires returns an error of kind
+    /// `io::ErrorKind::TimedOut`. A timeout of zero disables the write timeout, in which case
+    /// writes block until they complete. As with `set_timeout()`, partial milliseconds are rounded
+    /// up on Windows.
+    ///
+    /// The default implementation only accepts a timeout of zero and returns an `InvalidInput`
+    /// error otherwise.
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        if timeout == Duration::from_millis(0) {
+            Ok(())
+        }
+        else {
+            Err(unsupported("a write timeout"))
+        }
+    }
+
+    /// Returns the current inter-byte timeout, if any.
+    ///
+    /// The default implementation returns `None`.
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Sets the maximum time allowed between two received bytes.
+    ///
+    /// When an inter-byte timeout is set, a read that has received at least one byte returns as
+    /// soon as the line is idle for longer than the inter-byte timeout, even if the buffer hasn't
+    /// been filled. This allows reading variable-length messages that end with a gap of silence.
+    /// The timeout set with `set_timeout()` still bounds the read as a whole. Passing `None`
+    /// disables the inter-byte timeout.
+    ///
+    /// The default implementation only accepts `None`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support inter-byte timeouts.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        match timeout {
+            None => Ok(()),
+            Some(_) => Err(unsupported("an inter-byte timeout"))
+        }
+    }
+
+    /// Returns the current timeout mode.
+    ///
+    /// The default implementation returns `TimeoutMode::Total`.
+    fn timeout_mode(&self) -> TimeoutMode {
+        TimeoutMode::Total
+    }
+
+    /// Sets how the timeout set with `set_timeout()` is applied to reads.
+    ///
+    /// In `TimeoutMode::InterByte` and `TimeoutMode::None`, the inter-byte timeout set with
+    /// `set_inter_byte_timeout()` is ignored.
+    ///
+    /// The default implementation only accepts `TimeoutMode::Total`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support the timeout mode.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_timeout_mode(&mut self, mode: TimeoutMode) -> ::Result<()> {
+        match mode {
+            TimeoutMode::Total => Ok(()),
+            _ => Err(unsupported("this timeout mode"))
+        }
+    }
+
+    /// Enables or disables non-blocking reads.
+    ///
+    /// While non-blocking reads are enabled, a read returns immediately with the bytes that have
+    /// already been received. If no bytes are available, the read returns an error of kind
+    /// `io::ErrorKind::WouldBlock` instead of waiting for the timeout to expire.
+    ///
+    /// The default implementation only accepts `false`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support non-blocking reads.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        if nonblocking {
+            Err(unsupported("non-blocking mode"))
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if other processes are prevented from opening the device.
+    ///
+    /// The default implementation returns `false`.
+    fn is_exclusive(&self) -> bool {
+        false
+    }
+
+    /// Enables or disables exclusive access to the device.
+    ///
+    /// While exclusive access is enabled, other processes can't open the device. On Windows, the
+    /// share mode of a COM port is chosen when it's opened and can't be changed, so this function
+    /// has no effect and always succeeds. COM ports are opened for exclusive access unless
+    /// [`OpenOptions::shared()`](windows/struct.OpenOptions.html#method.shared) is set, and
+    /// `is_exclusive()` reports the mode that the port was opened with.
+    ///
+    /// The default implementation only accepts the mode returned by `is_exclusive()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device can't change to the requested mode.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_exclusive(&mut self, exclusive: bool) -> ::Result<()> {
+        if exclusive == SerialDevice::is_exclusive(self) {
+            Ok(())
+        }
+        else {
+            Err(unsupported("changing exclusive access"))
+        }
+    }
+
+    /// Enables or disables low-latency mode.
+    ///
+    /// Some USB serial adapters, notably those made by FTDI, hold received bytes for up to 16ms
+    /// before passing them on, which slows down request-response protocols. On Linux, enabling
+    /// low-latency mode makes the driver pass received bytes on immediately. On other platforms,
+    /// this function has no effect; on Windows, the latency of FTDI adapters is configured in the
+    /// driver's settings in the Device Manager instead.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_low_latency(&mut self, _low_latency: bool) -> ::Result<()> {
+        Ok(())
+    }
+
+    /// Controls whether the modem control lines are lowered when the device is closed.
+    ///
+    /// By default, closing a POSIX tty lowers DTR and RTS (the termios `HUPCL` flag), which resets
+    /// many attached devices. Disabling hang-up on close leaves the lines as they are, so a device
+    /// keeps running after the program exits. On Windows, the serial driver decides what happens
+    /// to the lines when a COM port is closed, so this function has no effect.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_hangup_on_close(&mut self, _hangup: bool) -> ::Result<()> {
+        Ok(())
+    }
+
+    /// Blocks until all output written to the device has been transmitted.
+    ///
+    /// This is stronger than `std::io::Write::flush()`, which only guarantees that data has been
+    /// handed from the operating system's buffers to the device driver. `drain()` waits until the
+    /// driver has finished transmitting the data, e.g., before switching the direction of an RS-485
+    /// transceiver. Depending on the device, the last bytes may still be in the hardware's transmit
+    /// FIFO when `drain()` returns.
+    ///
+    /// The wait is bounded by the write timeout set with `set_write_timeout()`.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the transmit queue could not be drained:
+    ///
+    /// * `InvalidInput` if the device doesn't support draining its output.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the output was not transmitted before the write timeout expired.
+    /// * `Io` for any other type of I/O error.
+    fn drain(&mut self) -> ::Result<()> {
+        Err(unsupported("draining the output"))
+    }
+
+    /// Discards data held in the device's buffers.
+    ///
+    /// Received data that has not yet been read, written data that has not yet been transmitted,
+    /// or both are discarded, depending on `buffer`.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the buffers could not be cleared:
+    ///
+    /// * `InvalidInput` if the device doesn't support clearing its buffers.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn clear(&mut self, _buffer: ClearBuffer) -> ::Result<()> {
+        Err(unsupported("clearing the buffers"))
+    }
+
+    /// Blocks until received data is available to read, without reading it.
+    ///
+    /// Returns `true` as soon as at least one byte can be read, or `false` if no data arrived
+    /// before `timeout` expired. A timeout of zero only checks whether data has already been
+    /// received. The timeouts set with `set_timeout()` don't apply.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support waiting for received data.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn wait_readable(&mut self, _timeout: Duration) -> ::Result<bool> {
+        Err(unsupported("waiting for received data"))
+    }
+
+    /// Returns the number of bytes that have been received and can be read without waiting.
+    ///
+    /// On Windows, this is the number of bytes in the driver's receive queue, the same count that
+    /// `COMPort::bytes_in()` returns.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't report the number of received bytes.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        Err(unsupported("counting received bytes"))
+    }
+
+    /// Sets the state of the RTS (Request To Send) control signal.
+    ///
+    /// Setting a value of `true` asserts the RTS control signal. `false` clears the signal.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the RTS control signal could not be set to the desired
+    /// state on the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_rts(&mut self, level: bool) -> ::Result<()>;
+
+    /// Sets the state of the DTR (Data Terminal Ready) control signal.
+    ///
+    /// Setting a value of `true` asserts the DTR control signal. `false` clears the signal.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the DTR control signal could not be set to the desired
+    /// state on the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_dtr(&mut self, level: bool) -> ::Result<()>;
+
+    /// Asserts a break condition on the transmit line.
+    ///
+    /// The transmit line is held in the spacing (logic low) state until the break condition is
+    /// released with `clear_break()`.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the break condition could not be set on the underlying
+    /// hardware:
+    ///
+    /// * `InvalidInput` if the device doesn't support break conditions.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_break(&mut self) -> ::Result<()> {
+        Err(unsupported("a break condition"))
+    }
+
+    /// Releases a break condition on the transmit line.
+    ///
+    /// The transmit line is returned to the marking (idle) state so that normal transmission can
+    /// resume.
+    ///
+    /// The default implementation does nothing, since the default `set_break()` never asserts a
+    /// break condition.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the break condition could not be cleared on the
+    /// underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn clear_break(&mut self) -> ::Result<()> {
+        Ok(())
+    }
+
+    /// Reads the state of all input control signals at once.
+    ///
+    /// The returned `ModemStatus` is a consistent snapshot of the CTS, DSR, RI, and CD control
+    /// signals, taken with a single query of the underlying hardware.
+    ///
+    /// The default implementation reads the control signals one at a time, so the snapshot may
+    /// not be consistent.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the control signals could not be read from
+    /// the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        Ok(ModemStatus {
+            cts: try!(SerialDevice::read_cts(self)),
+            dsr: try!(SerialDevice::read_dsr(self)),
+            ri: try!(SerialDevice::read_ri(self)),
+            cd: try!(SerialDevice::read_cd(self))
+        })
+    }
+
+    /// Blocks until one of the selected input control signals changes state.
+    ///
+    /// Returns the state of all input control signals after the change. The wait is bounded by
+    /// the timeout set with `set_timeout()`. A timeout of zero checks for a change once without
+    /// blocking.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// * `Io(TimedOut)` if none of the selected signals changed before the timeout expired.
+    /// * `InvalidInput` if no signals are selected.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn wait_modem_change(&mut self, _lines: ModemLines) -> ::Result<ModemStatus> {
+        Err(unsupported("waiting for modem changes"))
+    }
+
+    /// Reads the state of the CTS (Clear To Send) control signal.
+    ///
+    /// This function returns a boolean that indicates whether the CTS control signal is asserted.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the CTS control signal could not be read
+    /// from the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn read_cts(&mut self) -> ::Result<bool>;
+
+    /// Reads the state of the DSR (Data Set Ready) control signal.
+    ///
+    /// This function returns a boolean that indicates whether the DSR control signal is asserted.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the DSR control signal could not be read
+    /// from the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn read_dsr(&mut self) -> ::Result<bool>;
+
+    /// Reads the state of the RI (Ring Indicator) control signal.
+    ///
+    /// This function returns a boolean that indicates whether the RI control signal is asserted.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the RI control signal could not be read from
+    /// the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn read_ri(&mut self) -> ::Result<bool>;
+
+    /// Reads the state of the CD (Carrier Detect) control signal.
+    ///
+    /// This function returns a boolean that indicates whether the CD control signal is asserted.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the CD control signal could not be read from
+    /// the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn read_cd(&mut self) -> ::Result<bool>;
+}
+
+/// A trait for serial port devices.
+///
+/// Serial port input and output is implemented through the `std::io::Read` and `std::io::Write`
+/// traits. A timeout can be set with the `set_timeout()` method and applies to all subsequent read
+/// operations. A separate timeout for write operations can be set with `set_write_timeout()`.
+///
+/// The `SerialPort` trait exposes several common control signals. Each control signal is
+/// represented as a boolean, where `true` indicates that the signal is asserted.
+///
+/// The serial port will be closed when the value is dropped.
+pub trait SerialPort: io::Read+io::Write {
+    /// Returns the current timeout.
+    fn timeout(&self) -> Duration;
+
+    /// Sets the timeout for future I/O operations.
+    ///
+    /// By default, the timeout is a deadline for each read as a whole. Use `set_timeout_mode()`
+    /// to treat it as the maximum idle time between received bytes instead.
+    ///
+    /// A timeout of zero means that reads don't wait at all: a read returns the bytes that have
+    /// already been received, or an error of kind `io::ErrorKind::TimedOut` if there are none.
+    /// This differs from the Windows API, where a timeout of zero disables the timeout. Reads
+    /// that never time out are selected with `TimeoutMode::None` instead.
+    ///
+    /// Windows measures timeouts in whole milliseconds, so a timeout that isn't a whole number of
+    /// milliseconds is rounded up. A timeout shorter than a millisecond becomes one millisecond,
+    /// never zero.
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()>;
+
+    /// Returns the current write timeout.
+    ///
+    /// The default implementation returns zero, i.e., writes don't time out.
+    fn write_timeout(&self) -> Duration {
+        Duration::from_millis(0)
+    }
+
+    /// Sets the timeout for future write operations.
+    ///
+    /// A write that can not be completed before the timeout expires returns an error of kind
+    /// `io::ErrorKind::TimedOut`. A timeout of zero disables the write timeout, in which case
+    /// writes block until they complete. As with `set_timeout()`, partial milliseconds are rounded
+    /// up on Windows.
+    ///
+    /// The default implementation only accepts a timeout of zero and returns an `InvalidInput`
+    /// error otherwise.
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        if timeout == Duration::from_millis(0) {
+            Ok(())
+        }
+        else {
+            Err(unsupported("a write timeout"))
+        }
+    }
+
+    /// Returns the current inter-byte timeout, if any.
+    ///
+    /// The default implementation returns `None`.
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Sets the maximum time allowed between two received bytes.
+    ///
+    /// When an inter-byte timeout is set, a read that has received at least one byte returns as
+    /// soon as the line is idle for longer than the inter-byte timeout, even if the buffer hasn't
+    /// been filled. This allows reading variable-length messages that end with a gap of silence.
+    /// The timeout set with `set_timeout()` still bounds the read as a whole. Passing `None`
+    /// disables the inter-byte timeout.
+    ///
+    /// The default implementation only accepts `None`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support inter-byte timeouts.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        match timeout {
+            None => Ok(()),
+            Some(_) => Err(unsupported("an inter-byte timeout"))
+        }
+    }
+
+    /// Returns the current timeout mode.
+    ///
+    /// The default implementation returns `TimeoutMode::Total`.
+    fn timeout_mode(&self) -> TimeoutMode {
+        TimeoutMode::Total
+    }
+
+    /// Sets how the timeout set with `set_timeout()` is applied to reads.
+    ///
+    /// In `TimeoutMode::InterByte` and `TimeoutMode::None`, the inter-byte timeout set with
+    /// `set_inter_byte_timeout()` is ignored.
+    ///
+    /// The default implementation only accepts `TimeoutMode::Total`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support the timeout mode.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_timeout_mode(&mut self, mode: TimeoutMode) -> ::Result<()> {
+        match mode {
+            TimeoutMode::Total => Ok(()),
+            _ => Err(unsupported("this timeout mode"))
+        }
+    }
+
+    /// Enables or disables non-blocking reads.
+    ///
+    /// While non-blocking reads are enabled, a read returns immediately with the bytes that have
+    /// already been received. If no bytes are available, the read returns an error of kind
+    /// `io::ErrorKind::WouldBlock` instead of waiting for the timeout to expire.
+    ///
+    /// The default implementation only accepts `false`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support non-blocking reads.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        if nonblocking {
+            Err(unsupported("non-blocking mode"))
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if other processes are prevented from opening the device.
+    ///
+    /// The default implementation returns `false`.
+    fn is_exclusive(&self) -> bool {
+        false
+    }
+
+    /// Enables or disables exclusive access to the device.
+    ///
+    /// While exclusive access is enabled, other processes can't open the device. On Windows, the
+    /// share mode of a COM port is chosen when it's opened and can't be changed, so this function
+    /// has no effect and always succeeds. COM ports are opened for exclusive access unless
+    /// [`OpenOptions::shared()`](windows/struct.OpenOptions.html#method.shared) is set, and
+    /// `is_exclusive()` reports the mode that the port was opened with.
+    ///
+    /// The default implementation only accepts the mode returned by `is_exclusive()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device can't change to the requested mode.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_exclusive(&mut self, exclusive: bool) -> ::Result<()> {
+        if exclusive == SerialPort::is_exclusive(self) {
+            Ok(())
+        }
+        else {
+            Err(unsupported("changing exclusive access"))
+        }
+    }
+
+    /// Enables or disables low-latency mode.
+    ///
+    /// Some USB serial adapters, notably those made by FTDI, hold received bytes for up to 16ms
+    /// before passing them on, which slows down request-response protocols. On Linux, enabling
+    /// low-latency mode makes the driver pass received bytes on immediately. On other platforms,
+    /// this function has no effect; on Windows, the latency of FTDI adapters is configured in the
+    /// driver's settings in the Device Manager instead.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_low_latency(&mut self, _low_latency: bool) -> ::Result<()> {
+        Ok(())
+    }
+
+    /// Controls whether the modem control lines are lowered when the device is closed.
+    ///
+    /// By default, closing a POSIX tty lowers DTR and RTS (the termios `HUPCL` flag), which resets
+    /// many attached devices. Disabling hang-up on close leaves the lines as they are, so a device
+    /// keeps running after the program exits. On Windows, the serial driver decides what happens
+    /// to the lines when a COM port is closed, so this function has no effect.
+    ///
+    /// The default implementation does nothing.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_hangup_on_close(&mut self, _hangup: bool) -> ::Result<()> {
+        Ok(())
+    }
+
+    /// Configures a serial port device.
+    ///
+    /// ## Errors
+    ///
     /// This function returns an error if the settings could not be applied to the underlying
     /// hardware:
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
     /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
     /// * `Io` for any other type of I/O error.
-    fn write_settings(&mut self, settings: &Self::Settings) -> ::Result<()>;
+    fn configure(&mut self, settings: &PortSettings) -> ::Result<()>;
 
-    /// Returns the current timeout.
-    fn timeout(&self) -> Duration;
+    /// Alter the serial port's configuration.
+    ///
+    /// This method expects a function, which takes a mutable reference to the serial port's
+    /// configuration settings. The serial port's current settings, read from the device, are
+    /// yielded to the provided function. After the function returns, any changes made to the
+    /// settings object will be written back to the device.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the `setup` function returns an error or if there was an
+    /// error while reading or writing the device's configuration settings:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
+    /// * `Io` for any other type of I/O error.
+    /// * Any error returned by the `setup` function.
+    ///
+    /// ## Example
+    ///
+    /// The following is a function that toggles a serial port's settings between one and two stop
+    /// bits:
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use serial::prelude::*;
+    ///
+    /// fn toggle_stop_bits<T: SerialPort>(port: &mut T) -> serial::Result<()> {
+    ///     port.reconfigure(&|settings| {
+    ///         let stop_bits = match settings.stop_bits() {
+    ///             Some(serial::Stop1) => serial::Stop2,
+    ///             Some(serial::Stop1_5) | Some(serial::Stop2) | None => serial::Stop1
+    ///         };
+    ///
+    ///         settings.set_stop_bits(stop_bits);
+    ///         Ok(())
+    ///     })
+    /// }
+    /// ```
+    fn reconfigure(&mut self, setup: &Fn (&mut SerialPortSettings) -> ::Result<()>) -> ::Result<()>;
 
-    /// Sets the timeout for future I/O operations.
-    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()>;
+    /// Blocks until all output written to the device has been transmitted.
+    ///
+    /// This is stronger than `std::io::Write::flush()`, which only guarantees that data has been
+    /// handed from the operating system's buffers to the device driver. `drain()` waits until the
+    /// driver has finished transmitting the data, e.g., before switching the direction of an RS-485
+    /// transceiver. Depending on the device, the last bytes may still be in the hardware's transmit
+    /// FIFO when `drain()` returns.
+    ///
+    /// The wait is bounded by the write timeout set with `set_write_timeout()`.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the transmit queue could not be drained:
+    ///
+    /// * `InvalidInput` if the device doesn't support draining its output.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the output was not transmitted before the write timeout expired.
+    /// * `Io` for any other type of I/O error.
+    fn drain(&mut self) -> ::Result<()> {
+        Err(unsupported("draining the output"))
+    }
+
+    /// Writes an entire buffer and waits until it has been transmitted.
+    ///
+    /// This combines `write_all()` with `drain()`, so when it returns, the line is idle and it's
+    /// safe to, e.g., switch the direction of an RS-485 transceiver or change the settings.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the data was not written or transmitted before the write timeout
+    ///   expired.
+    /// * `Io` for any other type of I/O error.
+    fn write_all_drain(&mut self, buf: &[u8]) -> ::Result<()> {
+        try!(io::Write::write_all(self, buf));
+        self.drain()
+    }
+
+    /// Discards data held in the device's buffers.
+    ///
+    /// Received data that has not yet been read, written data that has not yet been transmitted,
+    /// or both are discarded, depending on `buffer`.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the buffers could not be cleared:
+    ///
+    /// * `InvalidInput` if the device doesn't support clearing its buffers.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn clear(&mut self, _buffer: ClearBuffer) -> ::Result<()> {
+        Err(unsupported("clearing the buffers"))
+    }
+
+    /// Blocks until received data is available to read, without reading it.
+    ///
+    /// Returns `true` as soon as at least one byte can be read, or `false` if no data arrived
+    /// before `timeout` expired. A timeout of zero only checks whether data has already been
+    /// received. The timeouts set with `set_timeout()` don't apply.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't support waiting for received data.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn wait_readable(&mut self, _timeout: Duration) -> ::Result<bool> {
+        Err(unsupported("waiting for received data"))
+    }
+
+    /// Returns the number of bytes that have been received and can be read without waiting.
+    ///
+    /// On Windows, this is the number of bytes in the driver's receive queue, the same count that
+    /// `COMPort::bytes_in()` returns.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the device doesn't report the number of received bytes.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        Err(unsupported("counting received bytes"))
+    }
 
     /// Sets the state of the RTS (Request To Send) control signal.
     ///
@@ -434,7 +1990,7 @@ pub trait SerialDevice: io::Read+io::Write {
     /// This function returns an error if the RTS control signal could not be set to the desired
     /// state on the underlying hardware:
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
     fn set_rts(&mut self, level: bool) -> ::Result<()>;
 
@@ -447,10 +2003,150 @@ pub trait SerialDevice: io::Read+io::Write {
     /// This function returns an error if the DTR control signal could not be set to the desired
     /// state on the underlying hardware:
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
     fn set_dtr(&mut self, level: bool) -> ::Result<()>;
 
+    /// Asserts a break condition on the transmit line.
+    ///
+    /// The transmit line is held in the spacing (logic low) state until the break condition is
+    /// released with `clear_break()`.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the break condition could not be set on the underlying
+    /// hardware:
+    ///
+    /// * `InvalidInput` if the device doesn't support break conditions.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn set_break(&mut self) -> ::Result<()> {
+        Err(unsupported("a break condition"))
+    }
+
+    /// Releases a break condition on the transmit line.
+    ///
+    /// The transmit line is returned to the marking (idle) state so that normal transmission can
+    /// resume.
+    ///
+    /// The default implementation does nothing, since the default `set_break()` never asserts a
+    /// break condition.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the break condition could not be cleared on the
+    /// underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn clear_break(&mut self) -> ::Result<()> {
+        Ok(())
+    }
+
+    /// Transmits a break condition for a fixed duration.
+    ///
+    /// The break condition is asserted, held for `duration`, and then released. The calling
+    /// thread is blocked for the length of the break.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the break condition could not be set or cleared on the
+    /// underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn send_break(&mut self, duration: Duration) -> ::Result<()> {
+        try!(self.set_break());
+        thread::sleep(duration);
+        self.clear_break()
+    }
+
+    /// Performs a sequence of control signal changes and delays.
+    ///
+    /// Many microcontroller boards are reset or put into their bootloader by toggling DTR and
+    /// RTS with specific timing. The steps are performed in order, and the sequence stops at the
+    /// first step that fails.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use serial::prelude::*;
+    /// use serial::ResetStep::*;
+    ///
+    /// let mut port = serial::open("COM1").unwrap();
+    ///
+    /// // ESP32 boards enter their bootloader if GPIO0 (DTR) is low while EN (RTS) is released
+    /// port.reset_sequence(&[
+    ///     SetDtr(false),
+    ///     SetRts(true),
+    ///     Delay(Duration::from_millis(100)),
+    ///     SetDtr(true),
+    ///     SetRts(false),
+    ///     Delay(Duration::from_millis(50)),
+    ///     SetDtr(false)
+    /// ]).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn reset_sequence(&mut self, sequence: &[ResetStep]) -> ::Result<()> {
+        for step in sequence {
+            match *step {
+                ResetStep::SetDtr(level) => try!(self.set_dtr(level)),
+                ResetStep::SetRts(level) => try!(self.set_rts(level)),
+                ResetStep::Delay(duration) => thread::sleep(duration),
+                ResetStep::Break(duration) => try!(self.send_break(duration))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the state of all input control signals at once.
+    ///
+    /// The returned `ModemStatus` is a consistent snapshot of the CTS, DSR, RI, and CD control
+    /// signals, taken with a single query of the underlying hardware.
+    ///
+    /// The default implementation reads the control signals one at a time, so the snapshot may
+    /// not be consistent.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the control signals could not be read from
+    /// the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        Ok(ModemStatus {
+            cts: try!(SerialPort::read_cts(self)),
+            dsr: try!(SerialPort::read_dsr(self)),
+            ri: try!(SerialPort::read_ri(self)),
+            cd: try!(SerialPort::read_cd(self))
+        })
+    }
+
+    /// Blocks until one of the selected input control signals changes state.
+    ///
+    /// Returns the state of all input control signals after the change. The wait is bounded by
+    /// the timeout set with `set_timeout()`. A timeout of zero checks for a change once without
+    /// blocking.
+    ///
+    /// The default implementation returns an `InvalidInput` error.
+    ///
+    /// ## Errors
+    ///
+    /// * `Io(TimedOut)` if none of the selected signals changed before the timeout expired.
+    /// * `InvalidInput` if no signals are selected.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn wait_modem_change(&mut self, _lines: ModemLines) -> ::Result<ModemStatus> {
+        Err(unsupported("waiting for modem changes"))
+    }
+
     /// Reads the state of the CTS (Clear To Send) control signal.
     ///
     /// This function returns a boolean that indicates whether the CTS control signal is asserted.
@@ -460,7 +2156,7 @@ pub trait SerialDevice: io::Read+io::Write {
     /// This function returns an error if the state of the CTS control signal could not be read
     /// from the underlying hardware:
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
     fn read_cts(&mut self) -> ::Result<bool>;
 
@@ -473,7 +2169,7 @@ pub trait SerialDevice: io::Read+io::Write {
     /// This function returns an error if the state of the DSR control signal could not be read
     /// from the underlying hardware:
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
     fn read_dsr(&mut self) -> ::Result<bool>;
 
@@ -486,221 +2182,728 @@ pub trait SerialDevice: io::Read+io::Write {
     /// This function returns an error if the state of the RI control signal could not be read from
     /// the underlying hardware:
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
     fn read_ri(&mut self) -> ::Result<bool>;
 
-    /// Reads the state of the CD (Carrier Detect) control signal.
+    /// Reads the state of the CD (Carrier Detect) control signal.
+    ///
+    /// This function returns a boolean that indicates whether the CD control signal is asserted.
+    ///
+    /// ## Errors
+    ///
+    /// This function returns an error if the state of the CD control signal could not be read from
+    /// the underlying hardware:
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    fn read_cd(&mut self) -> ::Result<bool>;
+
+    /// Reads bytes into `buf` until the delimiter `byte` is received.
+    ///
+    /// All bytes up to and including the delimiter are appended to `buf`. On success, the number
+    /// of bytes appended is returned. Bytes are read from the device one at a time, so no data
+    /// following the delimiter is consumed.
+    ///
+    /// ## Errors
+    ///
+    /// If the timeout expires before the delimiter is received, an error of kind
+    /// `io::ErrorKind::TimedOut` is returned. Any bytes received before the timeout remain
+    /// appended to `buf`, so a partial line can be recovered by comparing the length of `buf`
+    /// before and after the call. Any other I/O error is returned as well, in which case `buf`
+    /// also keeps the bytes received before the error.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use serial::prelude::*;
+    ///
+    /// let mut port = serial::open("COM1").unwrap();
+    /// let mut line = Vec::new();
+    ///
+    /// port.read_until(b'\n', &mut line).unwrap();
+    /// ```
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        read_until(self, byte, buf)
+    }
+
+    /// Returns an iterator over the bytes received by the serial port.
+    ///
+    /// Unlike `std::io::Read::bytes()`, the iterator ends when a read times out, i.e., when the
+    /// line has been idle for longer than the current timeout. Any other I/O error is yielded as
+    /// an `Err` item.
+    fn timed_bytes<'a>(&'a mut self) -> TimedBytes<'a, Self> where Self: Sized {
+        TimedBytes { port: self }
+    }
+
+    /// Reads exactly enough bytes to fill `buf` before the timeout expires.
+    ///
+    /// Unlike `std::io::Read::read_exact()`, the current timeout applies to the whole buffer, not
+    /// to each individual read, and the number of bytes received is reported when the buffer
+    /// couldn't be filled. The device's timeout is adjusted while reading and restored before
+    /// returning. A timeout of zero reads only the bytes that have already been received.
+    ///
+    /// ## Errors
+    ///
+    /// If `buf` couldn't be filled, the number of bytes that were read into the start of `buf` is
+    /// returned alongside the error, so that reading can be resumed later:
+    ///
+    /// * `io::ErrorKind::TimedOut` if the timeout expired before `buf` was filled.
+    /// * `io::ErrorKind::UnexpectedEof` if the device reported the end of the stream.
+    /// * Any other I/O error while reading.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::io;
+    /// use serial::prelude::*;
+    ///
+    /// let mut port = serial::open("COM1").unwrap();
+    /// let mut frame = [0u8; 16];
+    ///
+    /// match port.read_exact_timeout(&mut frame) {
+    ///     Ok(()) => println!("frame: {:?}", frame),
+    ///     Err((n, ref e)) if e.kind() == io::ErrorKind::TimedOut => println!("partial: {:?}", &frame[..n]),
+    ///     Err((_, e)) => panic!("{}", e)
+    /// }
+    /// ```
+    fn read_exact_timeout(&mut self, buf: &mut [u8]) -> std::result::Result<(), (usize, io::Error)> {
+        let timeout = self.timeout();
+
+        if timeout == Duration::from_millis(0) {
+            return read_exact_deadline(self, buf, None);
+        }
+
+        let result = read_exact_deadline(self, buf, Some(timeout));
+
+        match (self.set_timeout(timeout), result) {
+            (Err(e), Ok(())) => Err((buf.len(), e.into())),
+            (_, result) => result
+        }
+    }
+
+    /// Reads from the port with a one-time timeout.
     ///
-    /// This function returns a boolean that indicates whether the CD control signal is asserted.
+    /// The port's timeout is replaced with `timeout` for the duration of a single read and then
+    /// restored, even if the read fails. This avoids changing the timeout permanently for one
+    /// read that is expected to take longer, such as waiting for a device to boot.
     ///
     /// ## Errors
     ///
-    /// This function returns an error if the state of the CD control signal could not be read from
-    /// the underlying hardware:
+    /// * `io::ErrorKind::TimedOut` if no bytes were received before `timeout` expired.
+    /// * Any other I/O error while reading or changing the timeout.
     ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `Io` for any other type of I/O error.
-    fn read_cd(&mut self) -> ::Result<bool>;
-}
+    /// If the read fails, its error is returned even if the timeout also couldn't be restored. If
+    /// the read succeeds but the timeout can't be restored, the error from restoring the timeout
+    /// is returned, since the port is still using `timeout`.
+    fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        let previous = self.timeout();
+        try!(self.set_timeout(timeout));
 
-/// A trait for serial port devices.
-///
-/// Serial port input and output is implemented through the `std::io::Read` and `std::io::Write`
-/// traits. A timeout can be set with the `set_timeout()` method and applies to all subsequent I/O
-/// operations.
-///
-/// The `SerialPort` trait exposes several common control signals. Each control signal is
-/// represented as a boolean, where `true` indicates that the signal is asserted.
-///
-/// The serial port will be closed when the value is dropped.
-pub trait SerialPort: io::Read+io::Write {
-    /// Returns the current timeout.
-    fn timeout(&self) -> Duration;
+        let result = self.read(buf);
 
-    /// Sets the timeout for future I/O operations.
-    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()>;
+        match (self.set_timeout(previous), result) {
+            (Err(e), Ok(_)) => Err(e.into()),
+            (_, result) => result
+        }
+    }
 
-    /// Configures a serial port device.
+    /// Reads a burst of data, combining as many reads as needed until the line goes quiet.
+    ///
+    /// A single read of a large buffer often returns as soon as the first bytes arrive, so
+    /// collecting a fast stream takes many small reads. This function sets the inter-byte timeout
+    /// to `gap` so that each read gathers a burst in the driver, and keeps reading until `buf` is
+    /// full or no more data arrives within `gap`. The inter-byte timeout is restored afterwards.
+    ///
+    /// Waiting for the first byte is bounded by the read timeout, as for `read()`. Returns the
+    /// number of bytes read.
     ///
     /// ## Errors
     ///
-    /// This function returns an error if the settings could not be applied to the underlying
-    /// hardware:
+    /// * `io::ErrorKind::TimedOut` if no bytes were received before the read timeout expired.
+    /// * Any other I/O error while reading or changing the inter-byte timeout.
     ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
-    /// * `Io` for any other type of I/O error.
-    fn configure(&mut self, settings: &PortSettings) -> ::Result<()>;
+    /// An error that occurs after some bytes have been read ends the burst; the bytes are returned
+    /// and the error is left for the next read to report.
+    fn read_full_available(&mut self, buf: &mut [u8], gap: Duration) -> io::Result<usize> {
+        let previous = self.inter_byte_timeout();
+        try!(self.set_inter_byte_timeout(Some(gap)));
 
-    /// Alter the serial port's configuration.
+        let result = read_burst(self, buf, gap);
+
+        match (self.set_inter_byte_timeout(previous), result) {
+            (Err(e), Ok(_)) => Err(e.into()),
+            (_, result) => result
+        }
+    }
+
+    /// Reads until the line goes quiet, appending everything that was received to `buf`.
     ///
-    /// This method expects a function, which takes a mutable reference to the serial port's
-    /// configuration settings. The serial port's current settings, read from the device, are
-    /// yielded to the provided function. After the function returns, any changes made to the
-    /// settings object will be written back to the device.
+    /// This is for responses of unknown length, such as the output of an AT command. Whenever
+    /// data arrives, it's read and the wait starts over, so the function returns once no byte has
+    /// arrived for `idle`. If nothing arrives at all, it returns after `idle` without reading
+    /// anything. The inter-byte timeout is set to `idle` while reading and restored afterwards.
     ///
-    /// ## Errors
+    /// Returns the number of bytes appended to `buf`.
     ///
-    /// This function returns an error if the `setup` function returns an error or if there was an
-    /// error while reading or writing the device's configuration settings:
+    /// ## Errors
     ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
+    /// * `Disconnected` if the device was disconnected.
     /// * `Io` for any other type of I/O error.
-    /// * Any error returned by the `setup` function.
     ///
-    /// ## Example
+    /// Bytes that were read before an error occurred remain in `buf`.
     ///
-    /// The following is a function that toggles a serial port's settings between one and two stop
-    /// bits:
+    /// ## Example
     ///
     /// ```no_run
-    /// use std::io;
+    /// use std::io::prelude::*;
+    /// use std::time::Duration;
     /// use serial::prelude::*;
     ///
-    /// fn toggle_stop_bits<T: SerialPort>(port: &mut T) -> serial::Result<()> {
-    ///     port.reconfigure(&|settings| {
-    ///         let stop_bits = match settings.stop_bits() {
-    ///             Some(serial::Stop1)        => serial::Stop2,
-    ///             Some(serial::Stop2) | None => serial::Stop1
-    ///         };
+    /// let mut port = serial::open("COM1").unwrap();
+    /// port.write_all(b"ATI\r").unwrap();
     ///
-    ///         settings.set_stop_bits(stop_bits);
-    ///         Ok(())
-    ///     })
-    /// }
+    /// let mut reply = Vec::new();
+    /// port.read_to_end_timeout(&mut reply, Duration::from_millis(200)).unwrap();
     /// ```
-    fn reconfigure(&mut self, setup: &Fn (&mut SerialPortSettings) -> ::Result<()>) -> ::Result<()>;
+    fn read_to_end_timeout(&mut self, buf: &mut Vec<u8>, idle: Duration) -> ::Result<usize> {
+        let previous = self.inter_byte_timeout();
+        try!(self.set_inter_byte_timeout(Some(idle)));
 
-    /// Sets the state of the RTS (Request To Send) control signal.
+        let result = read_until_idle(self, buf, idle);
+
+        match (self.set_inter_byte_timeout(previous), result) {
+            (Err(e), Ok(_)) => Err(e),
+            (_, result) => result
+        }
+    }
+
+    /// Writes an entire buffer, retrying partial writes until `timeout` expires.
     ///
-    /// Setting a value of `true` asserts the RTS control signal. `false` clears the signal.
+    /// `std::io::Write::write_all()` gives up as soon as a single write times out, which happens
+    /// easily on a slow link or while flow control holds back transmission. This function keeps
+    /// writing the rest of the buffer until everything has been written or `timeout` has expired
+    /// for the buffer as a whole. The device's write timeout is adjusted while writing and
+    /// restored before returning. A timeout of zero waits indefinitely.
     ///
     /// ## Errors
     ///
-    /// This function returns an error if the RTS control signal could not be set to the desired
-    /// state on the underlying hardware:
+    /// If `buf` couldn't be written entirely, the number of bytes from the start of `buf` that
+    /// were written is returned alongside the error:
     ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `Io` for any other type of I/O error.
-    fn set_rts(&mut self, level: bool) -> ::Result<()>;
-
-    /// Sets the state of the DTR (Data Terminal Ready) control signal.
+    /// * `io::ErrorKind::TimedOut` if the timeout expired before all of `buf` was written.
+    /// * `io::ErrorKind::WriteZero` if the device stopped accepting data.
+    /// * Any other I/O error while writing.
     ///
-    /// Setting a value of `true` asserts the DTR control signal. `false` clears the signal.
+    /// ## Example
     ///
-    /// ## Errors
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use serial::prelude::*;
     ///
-    /// This function returns an error if the DTR control signal could not be set to the desired
-    /// state on the underlying hardware:
+    /// let mut port = serial::open("COM1").unwrap();
     ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `Io` for any other type of I/O error.
-    fn set_dtr(&mut self, level: bool) -> ::Result<()>;
+    /// if let Err((n, e)) = port.write_all_timeout(&[0x55; 4096], Duration::from_secs(5)) {
+    ///     println!("only {} bytes written: {}", n, e);
+    /// }
+    /// ```
+    fn write_all_timeout(&mut self, buf: &[u8], timeout: Duration) -> std::result::Result<(), (usize, io::Error)> {
+        if timeout == Duration::from_millis(0) {
+            return write_all_deadline(self, buf, None);
+        }
 
-    /// Reads the state of the CTS (Clear To Send) control signal.
+        let previous = self.write_timeout();
+        let result = write_all_deadline(self, buf, Some(timeout));
+
+        match (self.set_write_timeout(previous), result) {
+            (Err(e), Ok(())) => Err((buf.len(), e.into())),
+            (_, result) => result
+        }
+    }
+
+    /// Writes a buffer one byte at a time, pausing between bytes.
     ///
-    /// This function returns a boolean that indicates whether the CTS control signal is asserted.
+    /// Some old devices without flow control lose characters when they arrive back to back. This
+    /// function writes each byte, waits until it has been transmitted, and then waits for `gap`
+    /// before writing the next byte. The write timeout set with `set_write_timeout()` applies to
+    /// the buffer as a whole, including the gaps, and is restored before returning.
     ///
     /// ## Errors
     ///
-    /// This function returns an error if the state of the CTS control signal could not be read
-    /// from the underlying hardware:
-    ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the buffer was not transmitted before the write timeout expired.
     /// * `Io` for any other type of I/O error.
-    fn read_cts(&mut self) -> ::Result<bool>;
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use serial::prelude::*;
+    ///
+    /// let mut port = serial::open("COM1").unwrap();
+    /// port.write_spaced(b"ATZ\r", Duration::from_millis(20)).unwrap();
+    /// ```
+    fn write_spaced(&mut self, buf: &[u8], gap: Duration) -> ::Result<()> {
+        let timeout = self.write_timeout();
 
-    /// Reads the state of the DSR (Data Set Ready) control signal.
+        let result = if timeout == Duration::from_millis(0) {
+            write_spaced_deadline(self, buf, gap, None)
+        }
+        else {
+            write_spaced_deadline(self, buf, gap, Some(timeout))
+        };
+
+        match (self.set_write_timeout(timeout), result) {
+            (Err(e), Ok(())) => Err(e),
+            (_, result) => result
+        }
+    }
+
+    /// Sends a request and reads the response, both within a single timeout.
     ///
-    /// This function returns a boolean that indicates whether the DSR control signal is asserted.
+    /// Received data that hasn't been read yet is discarded first, so a late reply to an earlier
+    /// request isn't mistaken for the response. The request is then written and drained, and up
+    /// to `response.len()` bytes are read. `timeout` bounds the whole exchange. The read and write
+    /// timeouts of the port are restored before returning.
     ///
-    /// ## Errors
+    /// Without an inter-byte timeout, the read continues until `response` is full or the timeout
+    /// expires. To read a variable-length response that ends with a gap of silence, set an
+    /// inter-byte timeout with `set_inter_byte_timeout()` first; the read then ends as soon as the
+    /// line has been idle for that long.
     ///
-    /// This function returns an error if the state of the DSR control signal could not be read
-    /// from the underlying hardware:
+    /// Returns the number of response bytes read.
+    ///
+    /// ## Errors
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the request wasn't sent or no response was received before the
+    ///   timeout expired.
     /// * `Io` for any other type of I/O error.
-    fn read_dsr(&mut self) -> ::Result<bool>;
-
-    /// Reads the state of the RI (Ring Indicator) control signal.
     ///
-    /// This function returns a boolean that indicates whether the RI control signal is asserted.
+    /// ## Example
     ///
-    /// ## Errors
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use serial::prelude::*;
     ///
-    /// This function returns an error if the state of the RI control signal could not be read from
-    /// the underlying hardware:
+    /// let mut port = serial::open("COM1").unwrap();
+    /// let mut reply = [0u8; 64];
     ///
-    /// * `NoDevice` if the device was disconnected.
-    /// * `Io` for any other type of I/O error.
-    fn read_ri(&mut self) -> ::Result<bool>;
+    /// let n = port.transaction(b"AT\r", &mut reply, Duration::from_secs(1)).unwrap();
+    /// println!("{:?}", &reply[..n]);
+    /// ```
+    fn transaction(&mut self, request: &[u8], response: &mut [u8], timeout: Duration) -> ::Result<usize> {
+        let read_timeout = self.timeout();
+        let write_timeout = self.write_timeout();
 
-    /// Reads the state of the CD (Carrier Detect) control signal.
+        let result = transaction_device(self, request, response, timeout);
+
+        let restored = self.set_timeout(read_timeout)
+            .and_then(|()| self.set_write_timeout(write_timeout));
+
+        match (restored, result) {
+            (Err(e), Ok(_)) => Err(e),
+            (_, result) => result
+        }
+    }
+
+    /// Checks that data written to the port is received back unchanged.
     ///
-    /// This function returns a boolean that indicates whether the CD control signal is asserted.
+    /// This is meant for validating hardware with a loopback plug, which connects the port's
+    /// transmit and receive lines (pins 2 and 3 of a DB-9 connector). Both buffers are cleared,
+    /// a fixed test pattern is written, and the same number of bytes is read back with a timeout
+    /// of 500ms. The previous read timeout is restored before returning.
     ///
-    /// ## Errors
+    /// Returns `true` if the pattern was received intact and `false` if different bytes or too
+    /// few bytes were received, e.g., because no loopback plug is attached. The pattern is
+    /// received intact only if the current settings don't alter it, so software flow control
+    /// should be disabled.
     ///
-    /// This function returns an error if the state of the CD control signal could not be read from
-    /// the underlying hardware:
+    /// ## Errors
     ///
-    /// * `NoDevice` if the device was disconnected.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the pattern couldn't be written before the write timeout expired.
     /// * `Io` for any other type of I/O error.
-    fn read_cd(&mut self) -> ::Result<bool>;
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use serial::prelude::*;
+    ///
+    /// let mut port = serial::open("COM1").unwrap();
+    /// println!("loopback: {}", if port.self_test().unwrap() { "ok" } else { "FAILED" });
+    /// ```
+    fn self_test(&mut self) -> ::Result<bool> {
+        try!(self.clear(ClearBuffer::All));
+
+        let timeout = self.timeout();
+        let result = self_test_device(self);
+
+        match (self.set_timeout(timeout), result) {
+            (Err(e), Ok(_)) => Err(e),
+            (_, result) => result
+        }
+    }
+}
+
+impl<T> SerialPort for T where T: SerialDevice {
+    fn timeout(&self) -> Duration {
+        T::timeout(self)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        T::set_timeout(self, timeout)
+    }
+
+    fn write_timeout(&self) -> Duration {
+        T::write_timeout(self)
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        T::set_write_timeout(self, timeout)
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        T::inter_byte_timeout(self)
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        T::set_inter_byte_timeout(self, timeout)
+    }
+
+    fn timeout_mode(&self) -> TimeoutMode {
+        T::timeout_mode(self)
+    }
+
+    fn set_timeout_mode(&mut self, mode: TimeoutMode) -> ::Result<()> {
+        T::set_timeout_mode(self, mode)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        T::set_nonblocking(self, nonblocking)
+    }
+
+    fn is_exclusive(&self) -> bool {
+        T::is_exclusive(self)
+    }
+
+    fn set_exclusive(&mut self, exclusive: bool) -> ::Result<()> {
+        T::set_exclusive(self, exclusive)
+    }
+
+    fn set_low_latency(&mut self, low_latency: bool) -> ::Result<()> {
+        T::set_low_latency(self, low_latency)
+    }
+
+    fn set_hangup_on_close(&mut self, hangup: bool) -> ::Result<()> {
+        T::set_hangup_on_close(self, hangup)
+    }
+
+    fn configure(&mut self, settings: &PortSettings) -> ::Result<()> {
+        let mut device_settings = try!(T::read_settings(self));
+
+        try!(device_settings.set_baud_rate(settings.baud_rate));
+        device_settings.set_char_size(settings.char_size);
+        device_settings.set_parity(settings.parity);
+        device_settings.set_stop_bits(settings.stop_bits);
+        device_settings.set_flow_control(settings.flow_control);
+
+        T::write_settings(self, &device_settings)
+    }
+
+    fn reconfigure(&mut self, setup: &Fn (&mut SerialPortSettings) -> ::Result<()>) -> ::Result<()> {
+        let mut device_settings = try!(T::read_settings(self));
+        try!(setup(&mut device_settings));
+        T::write_settings(self, &device_settings)
+    }
+
+    fn drain(&mut self) -> ::Result<()> {
+        T::drain(self)
+    }
+
+    fn clear(&mut self, buffer: ClearBuffer) -> ::Result<()> {
+        T::clear(self, buffer)
+    }
+
+    fn wait_readable(&mut self, timeout: Duration) -> ::Result<bool> {
+        T::wait_readable(self, timeout)
+    }
+
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        T::bytes_available(self)
+    }
+
+    fn set_rts(&mut self, level: bool) -> ::Result<()> {
+        T::set_rts(self, level)
+    }
+
+    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
+        T::set_dtr(self, level)
+    }
+
+    fn set_break(&mut self) -> ::Result<()> {
+        T::set_break(self)
+    }
+
+    fn clear_break(&mut self) -> ::Result<()> {
+        T::clear_break(self)
+    }
+
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        T::modem_status(self)
+    }
+
+    fn wait_modem_change(&mut self, lines: ModemLines) -> ::Result<ModemStatus> {
+        T::wait_modem_change(self, lines)
+    }
+
+    fn read_cts(&mut self) -> ::Result<bool> {
+        T::read_cts(self)
+    }
+
+    fn read_dsr(&mut self) -> ::Result<bool> {
+        T::read_dsr(self)
+    }
+
+    fn read_ri(&mut self) -> ::Result<bool> {
+        T::read_ri(self)
+    }
+
+    fn read_cd(&mut self) -> ::Result<bool> {
+        T::read_cd(self)
+    }
+}
+
+// the error returned by the default implementations of the optional trait methods
+fn unsupported(operation: &str) -> ::Error {
+    ::Error::new(0, ::ErrorKind::InvalidInput, format!("{} is not supported by the device", operation))
+}
+
+fn read_exact_deadline<T: SerialPort + ?Sized>(port: &mut T, buf: &mut [u8], timeout: Option<Duration>) -> std::result::Result<(), (usize, io::Error)> {
+    let start = Instant::now();
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        if let Some(timeout) = timeout {
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                return Err((filled, io::Error::new(io::ErrorKind::TimedOut, "Operation timed out")));
+            }
+
+            // a zero timeout doesn't wait at all, so never let the remaining time round down to it
+            let remaining = cmp::max(timeout - elapsed, Duration::from_millis(1));
+
+            if let Err(e) = T::set_timeout(port, remaining) {
+                return Err((filled, e.into()));
+            }
+        }
+
+        match port.read(&mut buf[filled..]) {
+            Ok(0) => return Err((filled, io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"))),
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err((filled, e))
+        }
+    }
+
+    Ok(())
 }
 
-impl<T> SerialPort for T where T: SerialDevice {
-    fn timeout(&self) -> Duration {
-        T::timeout(self)
+// alternating bits, both extremes, and line endings, but no XON/XOFF characters
+const SELF_TEST_PATTERN: &[u8] = b"\x55\xaa\x00\xff\r\nserial-rs";
+
+fn read_burst<T: SerialPort + ?Sized>(port: &mut T, buf: &mut [u8], gap: Duration) -> io::Result<usize> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match port.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                if filled == 0 {
+                    return Err(e);
+                }
+
+                break;
+            }
+        }
+
+        // bytes have been read, so an error from waiting is left for the next read as well
+        if filled < buf.len() {
+            match T::wait_readable(port, gap) {
+                Ok(true) => (),
+                _ => break
+            }
+        }
     }
 
-    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
-        T::set_timeout(self, timeout)
+    Ok(filled)
+}
+
+fn read_until_idle<T: SerialPort + ?Sized>(port: &mut T, buf: &mut Vec<u8>, idle: Duration) -> ::Result<usize> {
+    let mut chunk = [0u8; 256];
+    let mut total = 0;
+
+    while try!(T::wait_readable(port, idle)) {
+        match port.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => {
+                buf.extend_from_slice(&chunk[..n]);
+                total += n;
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into())
+        }
     }
 
-    fn configure(&mut self, settings: &PortSettings) -> ::Result<()> {
-        let mut device_settings = try!(T::read_settings(self));
+    Ok(total)
+}
 
-        try!(device_settings.set_baud_rate(settings.baud_rate));
-        device_settings.set_char_size(settings.char_size);
-        device_settings.set_parity(settings.parity);
-        device_settings.set_stop_bits(settings.stop_bits);
-        device_settings.set_flow_control(settings.flow_control);
+fn transaction_device<T: SerialPort + ?Sized>(port: &mut T, request: &[u8], response: &mut [u8], timeout: Duration) -> ::Result<usize> {
+    let start = Instant::now();
 
-        T::write_settings(self, &device_settings)
+    try!(T::clear(port, ClearBuffer::Input));
+
+    // the write timeout is left at the remaining time, which also bounds drain()
+    try!(write_all_deadline(port, request, Some(timeout)).map_err(|(_, e)| e));
+    try!(T::drain(port));
+
+    let elapsed = start.elapsed();
+
+    if elapsed >= timeout {
+        return Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"));
     }
 
-    fn reconfigure(&mut self, setup: &Fn (&mut SerialPortSettings) -> ::Result<()>) -> ::Result<()> {
-        let mut device_settings = try!(T::read_settings(self));
-        try!(setup(&mut device_settings));
-        T::write_settings(self, &device_settings)
+    let remaining = cmp::max(timeout - elapsed, Duration::from_millis(1));
+
+    if T::inter_byte_timeout(port).is_some() {
+        // the device ends the read once the line has been idle for the inter-byte timeout
+        try!(T::set_timeout(port, remaining));
+        return port.read(response).map_err(::Error::from);
     }
 
-    fn set_rts(&mut self, level: bool) -> ::Result<()> {
-        T::set_rts(self, level)
+    match read_exact_deadline(port, response, Some(remaining)) {
+        Ok(()) => Ok(response.len()),
+        Err((n, ref e)) if n > 0 && e.kind() == io::ErrorKind::TimedOut => Ok(n),
+        Err((_, e)) => Err(e.into())
     }
+}
 
-    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
-        T::set_dtr(self, level)
+fn self_test_device<T: SerialPort + ?Sized>(port: &mut T) -> ::Result<bool> {
+    try!(io::Write::write_all(port, SELF_TEST_PATTERN));
+
+    let mut received = vec![0u8; SELF_TEST_PATTERN.len()];
+
+    match read_exact_deadline(port, &mut received, Some(Duration::from_millis(500))) {
+        Ok(()) => Ok(received == SELF_TEST_PATTERN),
+        Err((_, ref e)) if e.kind() == io::ErrorKind::TimedOut => Ok(false),
+        Err((_, e)) => Err(e.into())
     }
+}
 
-    fn read_cts(&mut self) -> ::Result<bool> {
-        T::read_cts(self)
+fn write_spaced_deadline<T: SerialPort + ?Sized>(port: &mut T, buf: &[u8], gap: Duration, timeout: Option<Duration>) -> ::Result<()> {
+    let start = Instant::now();
+
+    for i in 0..buf.len() {
+        if i > 0 {
+            thread::sleep(gap);
+        }
+
+        let elapsed = start.elapsed();
+
+        let remaining = match timeout {
+            Some(timeout) if elapsed >= timeout => {
+                return Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"));
+            },
+            Some(timeout) => Some(timeout - elapsed),
+            None => None
+        };
+
+        // the write timeout is left at the remaining time, which also bounds drain()
+        try!(write_all_deadline(port, &buf[i..i + 1], remaining).map_err(|(_, e)| e));
+        try!(T::drain(port));
     }
 
-    fn read_dsr(&mut self) -> ::Result<bool> {
-        T::read_dsr(self)
+    Ok(())
+}
+
+fn write_all_deadline<T: SerialPort + ?Sized>(port: &mut T, buf: &[u8], timeout: Option<Duration>) -> std::result::Result<(), (usize, io::Error)> {
+    let start = Instant::now();
+    let mut written = 0;
+
+    while written < buf.len() {
+        if let Some(timeout) = timeout {
+            let elapsed = start.elapsed();
+
+            if elapsed >= timeout {
+                return Err((written, io::Error::new(io::ErrorKind::TimedOut, "Operation timed out")));
+            }
+
+            // a zero timeout waits indefinitely, so never let the remaining time round down to it
+            let remaining = cmp::max(timeout - elapsed, Duration::from_millis(1));
+
+            if let Err(e) = T::set_write_timeout(port, remaining) {
+                return Err((written, e.into()));
+            }
+        }
+
+        match port.write(&buf[written..]) {
+            Ok(0) => return Err((written, io::Error::new(io::ErrorKind::WriteZero, "failed to write whole buffer"))),
+            Ok(n) => written += n,
+            // a timed out write may still have made progress, so retry until the deadline
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted || e.kind() == io::ErrorKind::TimedOut => continue,
+            Err(e) => return Err((written, e))
+        }
     }
 
-    fn read_ri(&mut self) -> ::Result<bool> {
-        T::read_ri(self)
+    Ok(())
+}
+
+fn read_until<R: io::Read + ?Sized>(reader: &mut R, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+    let start = buf.len();
+    let mut next = [0u8; 1];
+
+    loop {
+        match reader.read(&mut next) {
+            Ok(0) => return Ok(buf.len() - start),
+            Ok(_) => {
+                buf.push(next[0]);
+
+                if next[0] == byte {
+                    return Ok(buf.len() - start);
+                }
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e)
+        }
     }
+}
 
-    fn read_cd(&mut self) -> ::Result<bool> {
-        T::read_cd(self)
+/// An iterator over the bytes received by a serial port.
+///
+/// The iterator ends when a read times out. It is created by the
+/// [`timed_bytes()`](trait.SerialPort.html#method.timed_bytes) method on `SerialPort`.
+pub struct TimedBytes<'a, T: 'a> {
+    port: &'a mut T
+}
+
+impl<'a, T: io::Read> Iterator for TimedBytes<'a, T> {
+    type Item = io::Result<u8>;
+
+    fn next(&mut self) -> Option<io::Result<u8>> {
+        let mut next = [0u8; 1];
+
+        loop {
+            return match self.port.read(&mut next) {
+                Ok(0) => None,
+                Ok(_) => Some(Ok(next[0])),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(ref e) if e.kind() == io::ErrorKind::TimedOut => None,
+                Err(e) => Some(Err(e))
+            };
+        }
     }
 }
 
@@ -764,10 +2967,61 @@ pub trait SerialPortSettings {
 
     /// Sets the flow control mode.
     fn set_flow_control(&mut self, flow_control: FlowControl);
+
+    /// Returns the baud rate used for receiving.
+    ///
+    /// Settings that use a single baud rate for both directions return the same value as
+    /// `baud_rate()`. When the input and output baud rates differ, `baud_rate()` returns `None`
+    /// and the individual rates are available from `input_baud_rate()` and `output_baud_rate()`.
+    fn input_baud_rate(&self) -> Option<BaudRate> {
+        self.baud_rate()
+    }
+
+    /// Returns the baud rate used for transmitting.
+    ///
+    /// See `input_baud_rate()` for settings that use different rates in each direction.
+    fn output_baud_rate(&self) -> Option<BaudRate> {
+        self.baud_rate()
+    }
+
+    /// Sets the baud rate used for receiving, leaving the output baud rate unchanged.
+    ///
+    /// ## Errors
+    ///
+    /// Settings that use a single baud rate for both directions, such as those of Windows COM
+    /// ports, return an `InvalidInput` error if `baud_rate` differs from the current baud rate.
+    /// Otherwise, this function may return an `InvalidInput` error for the same reasons as
+    /// `set_baud_rate()`.
+    fn set_input_baud(&mut self, baud_rate: BaudRate) -> ::Result<()> {
+        check_split_baud_rate(self.baud_rate(), baud_rate)
+    }
+
+    /// Sets the baud rate used for transmitting, leaving the input baud rate unchanged.
+    ///
+    /// ## Errors
+    ///
+    /// Settings that use a single baud rate for both directions, such as those of Windows COM
+    /// ports, return an `InvalidInput` error if `baud_rate` differs from the current baud rate.
+    /// Otherwise, this function may return an `InvalidInput` error for the same reasons as
+    /// `set_baud_rate()`.
+    fn set_output_baud(&mut self, baud_rate: BaudRate) -> ::Result<()> {
+        check_split_baud_rate(self.baud_rate(), baud_rate)
+    }
+}
+
+fn check_split_baud_rate(current: Option<BaudRate>, requested: BaudRate) -> ::Result<()> {
+    // BaudOther(9600) and Baud9600 describe the same rate, so compare speeds
+    if current.map(|baud_rate| baud_rate.speed()) == Some(requested.speed()) {
+        Ok(())
+    }
+    else {
+        Err(::Error::new(0, ::ErrorKind::InvalidInput, "input and output baud rates must match"))
+    }
 }
 
 /// A device-indepenent implementation of serial port settings.
 #[derive(Debug,Copy,Clone,PartialEq,Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize,Deserialize))]
 pub struct PortSettings {
     /// Baud rate.
     pub baud_rate: BaudRate,
@@ -843,8 +3097,426 @@ impl SerialPortSettings for PortSettings {
 #[cfg(test)]
 mod tests {
     use std::default::Default;
+    use std::io;
     use super::*;
 
+    struct TimeoutReader {
+        chunks: Vec<&'static [u8]>
+    }
+
+    impl io::Read for TimeoutReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.chunks.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+            }
+
+            let n = ::std::cmp::min(buf.len(), self.chunks[0].len());
+            buf[..n].copy_from_slice(&self.chunks[0][..n]);
+            self.chunks[0] = &self.chunks[0][n..];
+
+            if self.chunks[0].is_empty() {
+                self.chunks.remove(0);
+            }
+
+            Ok(n)
+        }
+    }
+
+    // a device that only implements the required methods of SerialDevice
+    struct BasicDevice {
+        timeout: Duration,
+        cts: bool
+    }
+
+    impl io::Read for BasicDevice {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl io::Write for BasicDevice {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SerialDevice for BasicDevice {
+        type Settings = PortSettings;
+
+        fn read_settings(&self) -> ::Result<PortSettings> {
+            Ok(PortSettings::default())
+        }
+
+        fn write_settings(&mut self, _settings: &PortSettings) -> ::Result<()> {
+            Ok(())
+        }
+
+        fn timeout(&self) -> Duration {
+            self.timeout
+        }
+
+        fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+            self.timeout = timeout;
+            Ok(())
+        }
+
+        fn set_rts(&mut self, _level: bool) -> ::Result<()> {
+            Ok(())
+        }
+
+        fn set_dtr(&mut self, _level: bool) -> ::Result<()> {
+            Ok(())
+        }
+
+        fn read_cts(&mut self) -> ::Result<bool> {
+            Ok(self.cts)
+        }
+
+        fn read_dsr(&mut self) -> ::Result<bool> {
+            Ok(false)
+        }
+
+        fn read_ri(&mut self) -> ::Result<bool> {
+            Ok(false)
+        }
+
+        fn read_cd(&mut self) -> ::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn optional_methods_accept_their_default_settings() {
+        let mut device = BasicDevice { timeout: Duration::from_millis(100), cts: false };
+
+        assert_eq!(SerialPort::write_timeout(&device), Duration::from_millis(0));
+        assert_eq!(SerialPort::inter_byte_timeout(&device), None);
+        assert_eq!(SerialPort::timeout_mode(&device), TimeoutMode::Total);
+        assert!(!SerialPort::is_exclusive(&device));
+
+        assert!(SerialPort::set_write_timeout(&mut device, Duration::from_millis(0)).is_ok());
+        assert!(SerialPort::set_inter_byte_timeout(&mut device, None).is_ok());
+        assert!(SerialPort::set_timeout_mode(&mut device, TimeoutMode::Total).is_ok());
+        assert!(SerialPort::set_nonblocking(&mut device, false).is_ok());
+        assert!(SerialPort::set_exclusive(&mut device, false).is_ok());
+        assert!(SerialPort::set_low_latency(&mut device, true).is_ok());
+        assert!(SerialPort::set_hangup_on_close(&mut device, false).is_ok());
+        assert!(SerialPort::clear_break(&mut device).is_ok());
+    }
+
+    #[test]
+    fn optional_methods_reject_unsupported_operations() {
+        let mut device = BasicDevice { timeout: Duration::from_millis(100), cts: false };
+
+        assert_eq!(SerialPort::set_write_timeout(&mut device, Duration::from_millis(10)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::set_inter_byte_timeout(&mut device, Some(Duration::from_millis(10))).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::set_timeout_mode(&mut device, TimeoutMode::None).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::set_nonblocking(&mut device, true).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::set_exclusive(&mut device, true).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::drain(&mut device).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::clear(&mut device, ClearBuffer::All).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::wait_readable(&mut device, Duration::from_millis(0)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::bytes_available(&mut device).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::send_break(&mut device, Duration::from_millis(0)).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(SerialPort::wait_modem_change(&mut device, ModemLines::all()).unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn default_modem_status_reads_each_signal() {
+        let mut device = BasicDevice { timeout: Duration::from_millis(100), cts: true };
+
+        let status = SerialPort::modem_status(&mut device).unwrap();
+
+        assert!(status.cts);
+        assert!(!status.dsr);
+        assert!(!status.ri);
+        assert!(status.cd);
+    }
+
+    #[test]
+    fn write_all_drain_writes_entire_buffer() {
+        let mut port = loopback::LoopbackPort::new();
+        port.write_all_drain(b"drained").unwrap();
+
+        let mut buf = [0u8; 7];
+        io::Read::read_exact(&mut port, &mut buf).unwrap();
+        assert_eq!(&buf, b"drained");
+    }
+
+    #[test]
+    fn reset_sequence_performs_steps_in_order() {
+        let mut port = loopback::LoopbackPort::new();
+        port.reset_sequence(&[
+            ResetStep::SetDtr(true),
+            ResetStep::SetRts(true),
+            ResetStep::Delay(Duration::from_millis(1)),
+            ResetStep::SetDtr(false)
+        ]).unwrap();
+
+        assert!(!port.dtr());
+        assert!(port.rts());
+    }
+
+    #[test]
+    fn touch_1200bps_sets_baud_rate_and_drops_dtr() {
+        let mut port = loopback::LoopbackPort::new();
+        SerialPort::set_dtr(&mut port, true).unwrap();
+
+        touch_1200bps_device(&mut port).unwrap();
+        assert_eq!(port.read_settings().unwrap().baud_rate, Baud1200);
+        assert!(!port.dtr());
+    }
+
+    #[test]
+    fn single_rate_settings_reject_split_baud_rates() {
+        let mut settings = PortSettings::default();
+        settings.set_input_baud(BaudOther(9600)).unwrap();
+        assert_eq!(settings.output_baud_rate(), Some(Baud9600));
+
+        let err = settings.set_output_baud(Baud1200).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(settings.baud_rate(), Some(Baud9600));
+    }
+
+    #[test]
+    fn read_timeout_restores_previous_timeout() {
+        let mut port = loopback::LoopbackPort::new();
+        let previous = SerialPort::timeout(&port);
+
+        let mut buf = [0u8; 1];
+        let err = port.read_timeout(&mut buf, Duration::from_millis(1)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(SerialPort::timeout(&port), previous);
+    }
+
+    #[test]
+    fn read_full_available_collects_a_burst() {
+        let mut port = loopback::LoopbackPort::new();
+        io::Write::write_all(&mut port, b"burst").unwrap();
+
+        let mut buf = [0u8; 16];
+        let n = port.read_full_available(&mut buf, Duration::from_millis(5)).unwrap();
+        assert_eq!(&buf[..n], b"burst");
+        assert_eq!(SerialPort::inter_byte_timeout(&port), None);
+
+        let err = port.read_full_available(&mut buf, Duration::from_millis(5)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn modem_status_reports_changed_lines() {
+        let before = ModemStatus { cts: true, ..ModemStatus::default() };
+        let after = ModemStatus { cts: false, dsr: true, ..ModemStatus::default() };
+
+        let changed = after.changed(&before);
+        assert_eq!(changed, ModemLines { cts: true, dsr: true, ..ModemLines::default() });
+        assert!(changed.contains(after.asserted()));
+        assert_eq!(after.asserted() | before.asserted(), changed);
+    }
+
+    #[test]
+    fn read_to_end_timeout_reads_until_idle() {
+        let mut port = loopback::LoopbackPort::new();
+        io::Write::write_all(&mut port, &[0x42; 600]).unwrap();
+
+        let mut buf = b"> ".to_vec();
+        assert_eq!(port.read_to_end_timeout(&mut buf, Duration::from_millis(5)).unwrap(), 600);
+        assert_eq!(buf.len(), 602);
+        assert_eq!(port.read_to_end_timeout(&mut buf, Duration::from_millis(5)).unwrap(), 0);
+    }
+
+    #[test]
+    fn transaction_discards_stale_input_and_restores_timeouts() {
+        let mut port = loopback::LoopbackPort::new();
+        io::Write::write_all(&mut port, b"stale").unwrap();
+
+        let mut reply = [0u8; 8];
+        let n = port.transaction(b"AT\r", &mut reply, Duration::from_millis(20)).unwrap();
+        assert_eq!(&reply[..n], b"AT\r");
+        assert_eq!(SerialPort::timeout(&port), Duration::from_millis(100));
+        assert_eq!(SerialPort::write_timeout(&port), Duration::from_millis(0));
+    }
+
+    #[test]
+    fn is_disconnected_detects_converted_disconnected_errors() {
+        assert!(is_disconnected(&io::Error::from(Error::new(0, ErrorKind::Disconnected, "gone"))));
+        assert!(!is_disconnected(&io::Error::from(Error::new(0, ErrorKind::NoDevice, "absent"))));
+        assert!(!is_disconnected(&io::Error::new(io::ErrorKind::NotFound, "not found")));
+        assert!(!is_disconnected(&io::Error::new(io::ErrorKind::TimedOut, "Operation timed out")));
+    }
+
+    #[test]
+    fn converting_to_io_error_keeps_description() {
+        let err = io::Error::from(Error::new(0, ErrorKind::Disconnected, "gone"));
+
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        assert_eq!(err.to_string(), "gone");
+    }
+
+    #[test]
+    fn retry_busy_retries_only_busy_errors() {
+        let mut calls = 0;
+        let result: ::Result<()> = retry_busy(3, Duration::from_millis(1), || {
+            calls += 1;
+            match calls {
+                1 => Err(Error::new(32, ErrorKind::Busy, "busy")),
+                _ => Err(Error::new(2, ErrorKind::NoDevice, "gone"))
+            }
+        });
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::NoDevice);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn retry_busy_returns_last_error_after_all_attempts() {
+        let mut calls = 0;
+        let result: ::Result<()> = retry_busy(3, Duration::from_millis(1), || {
+            calls += 1;
+            Err(Error::new(32, ErrorKind::Busy, "busy"))
+        });
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Busy);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn port_exists_rejects_unknown_name() {
+        assert!(!port_exists("COM_DOES_NOT_EXIST"));
+    }
+
+    #[test]
+    fn find_usb_port_matches_vid_and_pid() {
+        let ports = vec![
+            PortInfo { port_name: "COM1".to_string(), vid: None, pid: None, port_type: SerialPortType::PciPort },
+            PortInfo { port_name: "COM3".to_string(), vid: Some(0x2341), pid: Some(0x0043), port_type: SerialPortType::Unknown },
+            PortInfo { port_name: "COM7".to_string(), vid: Some(0x0403), pid: Some(0x6001), port_type: SerialPortType::Unknown }
+        ];
+
+        let found = find_usb_port(ports.clone().into_iter().map(Ok), 0x0403, 0x6001).unwrap();
+        assert_eq!(found.map(|port| port.port_name), Some("COM7".to_string()));
+        assert_eq!(find_usb_port(ports.into_iter().map(Ok), 0x0403, 0x6015).unwrap(), None);
+    }
+
+    #[test]
+    fn baud_rate_from_speed_maps_non_standard_rates_to_baud_other() {
+        assert_eq!(BaudRate::from_speed(14400), BaudOther(14400));
+        assert_eq!(BaudRate::from_speed(230400), BaudOther(230400));
+        assert_eq!(BaudRate::from_speed(0), BaudOther(0));
+    }
+
+    #[test]
+    fn baud_rate_speed_round_trips_through_from_speed() {
+        for &speed in &[110, 300, 600, 1200, 2400, 4800, 9600, 14400, 19200, 38400, 57600, 115200, 250000] {
+            assert_eq!(BaudRate::from_speed(speed).speed(), speed);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn port_settings_round_trips_through_json() {
+        extern crate serde_json;
+
+        let settings = PortSettings {
+            baud_rate: BaudOther(250000),
+            char_size: Bits7,
+            parity: ParityEven,
+            stop_bits: Stop2,
+            flow_control: FlowHardware
+        };
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(json.contains("\"baud_rate\":250000"));
+        assert_eq!(serde_json::from_str::<PortSettings>(&json).unwrap(), settings);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn baud_rate_deserializes_from_number() {
+        extern crate serde_json;
+
+        assert_eq!(serde_json::from_str::<BaudRate>("115200").unwrap(), Baud115200);
+        assert_eq!(serde_json::from_str::<BaudRate>("14400").unwrap(), BaudOther(14400));
+    }
+
+    #[test]
+    fn read_until_stops_after_delimiter() {
+        let mut reader = TimeoutReader { chunks: vec![b"$GPGGA\n$GP"] };
+        let mut line = Vec::new();
+
+        assert_eq!(super::read_until(&mut reader, b'\n', &mut line).unwrap(), 7);
+        assert_eq!(line, b"$GPGGA\n");
+    }
+
+    #[test]
+    fn read_until_keeps_partial_line_on_timeout() {
+        let mut reader = TimeoutReader { chunks: vec![b"$GP"] };
+        let mut line = Vec::new();
+
+        let err = super::read_until(&mut reader, b'\n', &mut line).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(line, b"$GP");
+    }
+
+    #[test]
+    fn read_exact_timeout_fills_buffer() {
+        let mut port = loopback::LoopbackPort::new();
+        io::Write::write_all(&mut port, b"frame").unwrap();
+
+        let mut buf = [0u8; 5];
+        port.read_exact_timeout(&mut buf).unwrap();
+        assert_eq!(&buf, b"frame");
+    }
+
+    #[test]
+    fn read_exact_timeout_reports_partial_read() {
+        let mut port = loopback::LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_millis(5)).unwrap();
+        io::Write::write_all(&mut port, b"fra").unwrap();
+
+        let mut buf = [0u8; 5];
+        let (n, err) = port.read_exact_timeout(&mut buf).unwrap_err();
+        assert_eq!(n, 3);
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(SerialPort::timeout(&port), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn write_spaced_writes_every_byte() {
+        let mut port = loopback::LoopbackPort::new();
+        SerialPort::set_write_timeout(&mut port, Duration::from_secs(1)).unwrap();
+        port.write_spaced(b"slow", Duration::from_millis(1)).unwrap();
+        assert_eq!(SerialPort::write_timeout(&port), Duration::from_secs(1));
+
+        let mut buf = [0u8; 4];
+        io::Read::read_exact(&mut port, &mut buf).unwrap();
+        assert_eq!(&buf, b"slow");
+    }
+
+    #[test]
+    fn self_test_passes_on_loopback_port() {
+        let mut port = loopback::LoopbackPort::new();
+        io::Write::write_all(&mut port, b"stale").unwrap();
+
+        assert!(port.self_test().unwrap());
+        assert_eq!(SerialPort::timeout(&port), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn write_all_timeout_writes_entire_buffer() {
+        let mut port = loopback::LoopbackPort::new();
+        port.write_all_timeout(b"slow link", Duration::from_millis(5)).unwrap();
+        assert_eq!(SerialPort::write_timeout(&port), Duration::from_millis(0));
+
+        let mut buf = [0u8; 9];
+        io::Read::read_exact(&mut port, &mut buf).unwrap();
+        assert_eq!(&buf, b"slow link");
+    }
+
     #[test]
     fn port_settings_manipulates_baud_rate() {
         let mut settings: PortSettings = Default::default();
@@ -879,4 +3551,55 @@ mod tests {
         settings.set_flow_control(FlowSoftware);
         assert_eq!(settings.flow_control(), Some(FlowSoftware));
     }
+
+    #[test]
+    fn settings_round_trip_through_strings() {
+        for parity in &[ParityNone, ParityOdd, ParityEven, ParityMark, ParitySpace] {
+            assert_eq!(parity.to_string().parse::<Parity>().unwrap(), *parity);
+        }
+
+        for stop_bits in &[Stop1, Stop1_5, Stop2] {
+            assert_eq!(stop_bits.to_string().parse::<StopBits>().unwrap(), *stop_bits);
+        }
+
+        for char_size in &[Bits5, Bits6, Bits7, Bits8] {
+            assert_eq!(char_size.to_string().parse::<CharSize>().unwrap(), *char_size);
+        }
+
+        for flow_control in &[FlowNone, FlowSoftware, FlowHardware] {
+            assert_eq!(flow_control.to_string().parse::<FlowControl>().unwrap(), *flow_control);
+        }
+
+        assert_eq!(Baud9600.to_string(), "9600");
+        assert_eq!("4000000".parse::<BaudRate>().unwrap(), BaudOther(4000000));
+    }
+
+    #[test]
+    fn invalid_setting_strings_are_rejected() {
+        let err = "9".parse::<CharSize>().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("character size"));
+
+        assert!("0".parse::<BaudRate>().is_err());
+        assert!("-9600".parse::<BaudRate>().is_err());
+        assert!("3".parse::<StopBits>().is_err());
+        assert!("rts".parse::<FlowControl>().is_err());
+    }
+
+    #[test]
+    fn baud_rate_computes_duration_for_chars() {
+        assert_eq!(Baud9600.duration_for_chars(1, 10), Duration::new(0, 1_041_666));
+        assert_eq!(Baud9600.duration_for_chars(960, 10), Duration::from_secs(1));
+        assert_eq!(Baud115200.duration_for_chars(0, 10), Duration::new(0, 0));
+        assert_eq!(BaudOther(0).duration_for_chars(100, 10), Duration::new(0, 0));
+        assert_eq!(BaudOther(1).duration_for_chars(1 << 30, 11), Duration::from_secs(11 << 30));
+    }
+
+    #[test]
+    fn baud_rate_computes_duration_for_chars_at_huge_baud_rates() {
+        let baud = usize::MAX;
+
+        assert_eq!(BaudOther(baud).duration_for_chars(baud, 1), Duration::from_secs(1));
+        assert_eq!(BaudOther(baud).duration_for_chars(baud - 1, 1), Duration::new(0, 999_999_999));
+    }
 }
@@ -0,0 +1,487 @@
+//! An in-memory serial device for testing.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+use std::time::{Duration,Instant};
+
+use ::{SerialDevice,PortSettings,ModemStatus,ModemLines,ClearBuffer,TimeoutMode};
+
+
+/// A serial device that echoes written bytes back to its read side.
+///
+/// `LoopbackPort` behaves like a serial port with its transmit line wired to its receive line. It
+/// doesn't require any hardware, which makes it useful for testing code that talks to serial
+/// ports. Bytes that are written to the port become readable after an optional latency, reads
+/// honor the timeout and non-blocking mode, and the input control signals can be set by the test
+/// with `set_modem_status()`.
+///
+/// ```
+/// use std::io::prelude::*;
+/// use serial::loopback::LoopbackPort;
+///
+/// let mut port = LoopbackPort::new();
+/// port.write_all(b"ping").unwrap();
+///
+/// let mut buf = [0u8; 4];
+/// port.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"ping");
+/// ```
+#[derive(Debug)]
+pub struct LoopbackPort {
+    buffer: VecDeque<(Instant, u8)>,
+    latency: Duration,
+    settings: PortSettings,
+    timeout: Duration,
+    write_timeout: Duration,
+    inter_byte_timeout: Option<Duration>,
+    timeout_mode: TimeoutMode,
+    nonblocking: bool,
+    exclusive: bool,
+    rts: bool,
+    dtr: bool,
+    line_break: bool,
+    modem_status: ModemStatus
+}
+
+impl LoopbackPort {
+    /// Creates a new loopback port.
+    ///
+    /// The port starts with the default `PortSettings`, a timeout of 100ms, no latency, and all
+    /// control signals cleared.
+    pub fn new() -> Self {
+        LoopbackPort {
+            buffer: VecDeque::new(),
+            latency: Duration::from_millis(0),
+            settings: PortSettings::default(),
+            timeout: Duration::from_millis(100),
+            write_timeout: Duration::from_millis(0),
+            inter_byte_timeout: None,
+            timeout_mode: TimeoutMode::Total,
+            nonblocking: false,
+            exclusive: false,
+            rts: false,
+            dtr: false,
+            line_break: false,
+            modem_status: ModemStatus::default()
+        }
+    }
+
+    /// Sets the delay between a byte being written and it becoming readable.
+    pub fn set_latency(&mut self, latency: Duration) {
+        self.latency = latency;
+    }
+
+    /// Sets the state of the input control signals reported by the port.
+    pub fn set_modem_status(&mut self, status: ModemStatus) {
+        self.modem_status = status;
+    }
+
+    /// Returns the current state of the RTS control signal.
+    pub fn rts(&self) -> bool {
+        self.rts
+    }
+
+    /// Returns the current state of the DTR control signal.
+    pub fn dtr(&self) -> bool {
+        self.dtr
+    }
+
+    /// Returns `true` if a break condition is currently asserted.
+    pub fn is_break(&self) -> bool {
+        self.line_break
+    }
+
+    fn ready(&self, now: Instant) -> usize {
+        self.buffer.iter().take_while(|&&(at, _)| at <= now).count()
+    }
+}
+
+impl Default for LoopbackPort {
+    fn default() -> Self {
+        LoopbackPort::new()
+    }
+}
+
+impl io::Read for LoopbackPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // a timeout too long to be represented as an Instant has no deadline
+        let deadline = Instant::now().checked_add(self.timeout);
+        let mut ready = self.ready(Instant::now());
+
+        if ready == 0 {
+            if self.nonblocking {
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "Operation would block"));
+            }
+
+            if self.timeout == Duration::from_millis(0) && self.timeout_mode != TimeoutMode::None {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+            }
+
+            // nothing can be written while the port is borrowed, so the next byte is either
+            // already buffered or will never arrive
+            let wake = match (self.buffer.front(), self.timeout_mode, deadline) {
+                (Some(&(at, _)), TimeoutMode::Total, Some(deadline)) => cmp::min(at, deadline),
+                (Some(&(at, _)), _, _) => at,
+                (None, TimeoutMode::Total, Some(deadline)) => deadline,
+                // waiting indefinitely for a byte that will never arrive would hang the caller
+                (None, _, _) => return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"))
+            };
+
+            let now = Instant::now();
+
+            if wake > now {
+                thread::sleep(wake - now);
+            }
+
+            ready = self.ready(Instant::now());
+        }
+
+        if ready == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+        }
+
+        let len = cmp::min(ready, buf.len());
+
+        for byte in buf[..len].iter_mut() {
+            *byte = self.buffer.pop_front().unwrap().1;
+        }
+
+        Ok(len)
+    }
+}
+
+impl io::Write for LoopbackPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let at = Instant::now() + self.latency;
+        self.buffer.extend(buf.iter().map(|&byte| (at, byte)));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialDevice for LoopbackPort {
+    type Settings = PortSettings;
+
+    fn read_settings(&self) -> ::Result<PortSettings> {
+        Ok(self.settings)
+    }
+
+    fn write_settings(&mut self, settings: &PortSettings) -> ::Result<()> {
+        self.settings = *settings;
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.write_timeout
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        self.write_timeout = timeout;
+        Ok(())
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.inter_byte_timeout
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        // bytes that are ready are always returned together, so there's never a gap to time out
+        self.inter_byte_timeout = timeout;
+        Ok(())
+    }
+
+    fn timeout_mode(&self) -> TimeoutMode {
+        self.timeout_mode
+    }
+
+    fn set_timeout_mode(&mut self, mode: TimeoutMode) -> ::Result<()> {
+        self.timeout_mode = mode;
+        Ok(())
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        self.nonblocking = nonblocking;
+        Ok(())
+    }
+
+    fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    fn set_exclusive(&mut self, exclusive: bool) -> ::Result<()> {
+        self.exclusive = exclusive;
+        Ok(())
+    }
+
+    fn set_low_latency(&mut self, _low_latency: bool) -> ::Result<()> {
+        Ok(())
+    }
+
+    fn set_hangup_on_close(&mut self, _hangup: bool) -> ::Result<()> {
+        Ok(())
+    }
+
+    fn drain(&mut self) -> ::Result<()> {
+        Ok(())
+    }
+
+    fn clear(&mut self, buffer: ClearBuffer) -> ::Result<()> {
+        // written bytes are delivered to the input side immediately, so only input is buffered
+        match buffer {
+            ClearBuffer::Input | ClearBuffer::All => self.buffer.clear(),
+            ClearBuffer::Output => ()
+        }
+
+        Ok(())
+    }
+
+    fn wait_readable(&mut self, timeout: Duration) -> ::Result<bool> {
+        let now = Instant::now();
+
+        // as in read(), no bytes can arrive other than those that are already buffered, so
+        // without a representable deadline there is nothing to wait for
+        let wake = match (self.buffer.front(), now.checked_add(timeout)) {
+            (Some(&(at, _)), Some(deadline)) => cmp::min(at, deadline),
+            (Some(&(at, _)), None) => at,
+            (None, Some(deadline)) => deadline,
+            (None, None) => return Ok(false)
+        };
+
+        if wake > now {
+            thread::sleep(wake - now);
+        }
+
+        Ok(self.ready(Instant::now()) > 0)
+    }
+
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        Ok(self.ready(Instant::now()))
+    }
+
+    fn set_rts(&mut self, level: bool) -> ::Result<()> {
+        self.rts = level;
+        Ok(())
+    }
+
+    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
+        self.dtr = level;
+        Ok(())
+    }
+
+    fn set_break(&mut self) -> ::Result<()> {
+        self.line_break = true;
+        Ok(())
+    }
+
+    fn clear_break(&mut self) -> ::Result<()> {
+        self.line_break = false;
+        Ok(())
+    }
+
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        Ok(self.modem_status)
+    }
+
+    fn wait_modem_change(&mut self, lines: ModemLines) -> ::Result<ModemStatus> {
+        if lines.is_empty() {
+            return Err(::Error::new(0, ::ErrorKind::InvalidInput, "no modem lines selected"));
+        }
+
+        // the modem status can only be changed through a mutable borrow, so it can't change now
+        thread::sleep(self.timeout);
+        Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"))
+    }
+
+    fn read_cts(&mut self) -> ::Result<bool> {
+        Ok(self.modem_status.cts)
+    }
+
+    fn read_dsr(&mut self) -> ::Result<bool> {
+        Ok(self.modem_status.dsr)
+    }
+
+    fn read_ri(&mut self) -> ::Result<bool> {
+        Ok(self.modem_status.ri)
+    }
+
+    fn read_cd(&mut self) -> ::Result<bool> {
+        Ok(self.modem_status.cd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::io::prelude::*;
+    use std::time::{Duration,Instant};
+
+    use super::*;
+    use ::SerialPort;
+
+    #[test]
+    fn loopback_port_echoes_written_bytes() {
+        let mut port = LoopbackPort::new();
+        port.write_all(b"hello").unwrap();
+
+        let mut buf = [0u8; 8];
+        assert_eq!(port.read(&mut buf).unwrap(), 5);
+        assert_eq!(&buf[..5], b"hello");
+    }
+
+    #[test]
+    fn loopback_port_passes_line_endings_and_control_characters() {
+        let mut port = LoopbackPort::new();
+        port.write_all(&[0x0D, 0x0A, 0x03]).unwrap();
+
+        let mut buf = [0u8; 3];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [0x0D, 0x0A, 0x03]);
+    }
+
+    #[test]
+    fn loopback_port_times_out_when_empty() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_millis(1)).unwrap();
+
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn loopback_port_returns_immediately_with_zero_timeout() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_millis(0)).unwrap();
+        SerialPort::set_timeout_mode(&mut port, TimeoutMode::InterByte).unwrap();
+        port.set_latency(Duration::from_secs(10));
+        port.write_all(b"x").unwrap();
+
+        let start = Instant::now();
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn loopback_port_would_block_when_nonblocking() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_nonblocking(&mut port, true).unwrap();
+
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn loopback_port_delivers_after_latency() {
+        let mut port = LoopbackPort::new();
+        port.set_latency(Duration::from_millis(5));
+        port.write_all(b"x").unwrap();
+
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], b'x');
+    }
+
+    #[test]
+    fn loopback_port_waits_past_timeout_in_inter_byte_mode() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_millis(1)).unwrap();
+        SerialPort::set_timeout_mode(&mut port, TimeoutMode::InterByte).unwrap();
+        port.set_latency(Duration::from_millis(10));
+        port.write_all(b"x").unwrap();
+
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap(), 1);
+    }
+
+    #[test]
+    fn loopback_port_times_out_waiting_for_modem_change() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_millis(1)).unwrap();
+
+        let err = SerialPort::wait_modem_change(&mut port, ::ModemLines::all()).unwrap_err();
+        assert_eq!(err.kind(), ::ErrorKind::Io(io::ErrorKind::TimedOut));
+    }
+
+    #[test]
+    fn loopback_port_checks_modem_change_once_with_zero_timeout() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_millis(0)).unwrap();
+
+        let start = Instant::now();
+        let err = SerialPort::wait_modem_change(&mut port, ::ModemLines::all()).unwrap_err();
+
+        assert_eq!(err.kind(), ::ErrorKind::Io(io::ErrorKind::TimedOut));
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn loopback_port_clears_input() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_nonblocking(&mut port, true).unwrap();
+        port.write_all(b"stale").unwrap();
+        SerialPort::clear(&mut port, ClearBuffer::All).unwrap();
+
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+    }
+
+    #[test]
+    fn loopback_port_waits_for_readable_data() {
+        let mut port = LoopbackPort::new();
+        port.set_latency(Duration::from_millis(5));
+        assert!(!SerialPort::wait_readable(&mut port, Duration::from_millis(1)).unwrap());
+
+        port.write_all(b"x").unwrap();
+        assert!(!SerialPort::wait_readable(&mut port, Duration::from_millis(0)).unwrap());
+        assert!(SerialPort::wait_readable(&mut port, Duration::from_millis(100)).unwrap());
+        assert_eq!(port.ready(Instant::now()), 1);
+    }
+
+    #[test]
+    fn loopback_port_waits_for_readable_data_with_huge_timeout() {
+        let mut port = LoopbackPort::new();
+        assert!(!SerialPort::wait_readable(&mut port, Duration::from_secs(u64::MAX)).unwrap());
+
+        port.set_latency(Duration::from_millis(5));
+        port.write_all(b"x").unwrap();
+        assert!(SerialPort::wait_readable(&mut port, Duration::from_secs(u64::MAX)).unwrap());
+    }
+
+    #[test]
+    fn loopback_port_reports_injected_modem_status() {
+        let mut port = LoopbackPort::new();
+        port.set_modem_status(ModemStatus { cts: true, ..ModemStatus::default() });
+
+        assert!(SerialPort::read_cts(&mut port).unwrap());
+        assert!(!SerialPort::read_dsr(&mut port).unwrap());
+    }
+
+    #[test]
+    fn loopback_port_reads_with_huge_timeout() {
+        let mut port = LoopbackPort::new();
+        SerialPort::set_timeout(&mut port, Duration::from_secs(u64::MAX)).unwrap();
+        port.write_all(b"x").unwrap();
+
+        let mut buf = [0u8; 1];
+        assert_eq!(port.read(&mut buf).unwrap(), 1);
+        assert_eq!(port.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+}
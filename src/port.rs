@@ -0,0 +1,379 @@
+use std::ffi::OsStr;
+use std::io;
+use std::time::Duration;
+
+use ::{SerialDevice,SerialPortSettings,SystemPort,ModemStatus,ModemLines,ClearBuffer,TimeoutMode};
+use ::{BaudRate,CharSize,Parity,StopBits,FlowControl};
+
+
+/// A serial port that keeps track of its name and settings.
+///
+/// `Port` wraps a serial device together with the name it was opened with and a copy of its
+/// settings. Each setting can be read without querying the device, and changed with a single
+/// call that reads the device's settings, modifies them, and writes them back.
+///
+/// `Port` implements `SerialDevice`, so the full [`SerialPort`](trait.SerialPort.html) interface
+/// is available as well. Settings written through that interface are reflected by the accessors.
+///
+/// ```no_run
+/// use std::io::prelude::*;
+///
+/// let mut port = serial::Port::open("COM1").unwrap();
+/// port.set_baud_rate(serial::Baud115200).unwrap();
+/// port.write_all(b"hello").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Port<T: SerialDevice = SystemPort> {
+    device: T,
+    name: String,
+    settings: T::Settings
+}
+
+impl Port<SystemPort> {
+    /// Opens a native serial port.
+    ///
+    /// The name is interpreted the same way as by [`serial::open()`](fn.open.html).
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other error while opening or initializing the device.
+    pub fn open<S: AsRef<OsStr> + ?Sized>(port: &S) -> ::Result<Self> {
+        let device = try!(::open(port));
+        Port::new(device, port.as_ref().to_string_lossy().into_owned())
+    }
+}
+
+impl<T: SerialDevice> Port<T> {
+    /// Wraps a serial device that has already been opened.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error while reading the device's settings.
+    pub fn new(device: T, name: String) -> ::Result<Self> {
+        let settings = try!(device.read_settings());
+
+        Ok(Port {
+            device: device,
+            name: name,
+            settings: settings
+        })
+    }
+
+    /// Returns the name of the port.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the settings that were last read from or written to the device.
+    pub fn settings(&self) -> &T::Settings {
+        &self.settings
+    }
+
+    /// Returns the baud rate.
+    pub fn baud_rate(&self) -> Option<BaudRate> {
+        self.settings.baud_rate()
+    }
+
+    /// Returns the character size.
+    pub fn char_size(&self) -> Option<CharSize> {
+        self.settings.char_size()
+    }
+
+    /// Returns the parity-checking mode.
+    pub fn parity(&self) -> Option<Parity> {
+        self.settings.parity()
+    }
+
+    /// Returns the number of stop bits.
+    pub fn stop_bits(&self) -> Option<StopBits> {
+        self.settings.stop_bits()
+    }
+
+    /// Returns the flow control mode.
+    pub fn flow_control(&self) -> Option<FlowControl> {
+        self.settings.flow_control()
+    }
+
+    /// Changes the baud rate.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if the baud rate is not supported by the device.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_baud_rate(&mut self, baud_rate: BaudRate) -> ::Result<()> {
+        self.modify(|settings| settings.set_baud_rate(baud_rate))
+    }
+
+    /// Changes the character size.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if the character size is not supported by the device.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_char_size(&mut self, char_size: CharSize) -> ::Result<()> {
+        self.modify(|settings| {
+            settings.set_char_size(char_size);
+            Ok(())
+        })
+    }
+
+    /// Changes the parity-checking mode.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if the parity mode is not supported by the device.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_parity(&mut self, parity: Parity) -> ::Result<()> {
+        self.modify(|settings| {
+            settings.set_parity(parity);
+            Ok(())
+        })
+    }
+
+    /// Changes the number of stop bits.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if the number of stop bits is not supported by the device.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_stop_bits(&mut self, stop_bits: StopBits) -> ::Result<()> {
+        self.modify(|settings| {
+            settings.set_stop_bits(stop_bits);
+            Ok(())
+        })
+    }
+
+    /// Changes the flow control mode.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if the flow control mode is not supported by the device.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_flow_control(&mut self, flow_control: FlowControl) -> ::Result<()> {
+        self.modify(|settings| {
+            settings.set_flow_control(flow_control);
+            Ok(())
+        })
+    }
+
+    /// Returns a reference to the underlying device.
+    pub fn get_ref(&self) -> &T {
+        &self.device
+    }
+
+    /// Returns a mutable reference to the underlying device.
+    ///
+    /// Settings that are changed directly on the device aren't reflected by the accessors of
+    /// `Port` until the settings are written through `Port` again.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.device
+    }
+
+    /// Unwraps the underlying device.
+    pub fn into_inner(self) -> T {
+        self.device
+    }
+
+    fn modify<F: FnOnce(&mut T::Settings) -> ::Result<()>>(&mut self, setup: F) -> ::Result<()> {
+        let mut settings = try!(self.device.read_settings());
+        try!(setup(&mut settings));
+        self.write_settings(&settings)
+    }
+}
+
+impl<T: SerialDevice> io::Read for Port<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.device.read(buf)
+    }
+}
+
+impl<T: SerialDevice> io::Write for Port<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.device.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        self.device.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.device.flush()
+    }
+}
+
+impl<T: SerialDevice> SerialDevice for Port<T> {
+    type Settings = T::Settings;
+
+    fn read_settings(&self) -> ::Result<T::Settings> {
+        self.device.read_settings()
+    }
+
+    fn write_settings(&mut self, settings: &T::Settings) -> ::Result<()> {
+        try!(self.device.write_settings(settings));
+
+        // the device may adjust the settings, so keep what it reports rather than what was asked
+        self.settings = try!(self.device.read_settings());
+        Ok(())
+    }
+
+    fn timeout(&self) -> Duration {
+        self.device.timeout()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        self.device.set_timeout(timeout)
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.device.write_timeout()
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        self.device.set_write_timeout(timeout)
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.device.inter_byte_timeout()
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        self.device.set_inter_byte_timeout(timeout)
+    }
+
+    fn timeout_mode(&self) -> TimeoutMode {
+        self.device.timeout_mode()
+    }
+
+    fn set_timeout_mode(&mut self, mode: TimeoutMode) -> ::Result<()> {
+        self.device.set_timeout_mode(mode)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        self.device.set_nonblocking(nonblocking)
+    }
+
+    fn is_exclusive(&self) -> bool {
+        self.device.is_exclusive()
+    }
+
+    fn set_exclusive(&mut self, exclusive: bool) -> ::Result<()> {
+        self.device.set_exclusive(exclusive)
+    }
+
+    fn set_low_latency(&mut self, low_latency: bool) -> ::Result<()> {
+        self.device.set_low_latency(low_latency)
+    }
+
+    fn set_hangup_on_close(&mut self, hangup: bool) -> ::Result<()> {
+        self.device.set_hangup_on_close(hangup)
+    }
+
+    fn drain(&mut self) -> ::Result<()> {
+        self.device.drain()
+    }
+
+    fn clear(&mut self, buffer: ClearBuffer) -> ::Result<()> {
+        self.device.clear(buffer)
+    }
+
+    fn wait_readable(&mut self, timeout: Duration) -> ::Result<bool> {
+        self.device.wait_readable(timeout)
+    }
+
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        self.device.bytes_available()
+    }
+
+    fn set_rts(&mut self, level: bool) -> ::Result<()> {
+        self.device.set_rts(level)
+    }
+
+    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
+        self.device.set_dtr(level)
+    }
+
+    fn set_break(&mut self) -> ::Result<()> {
+        self.device.set_break()
+    }
+
+    fn clear_break(&mut self) -> ::Result<()> {
+        self.device.clear_break()
+    }
+
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        self.device.modem_status()
+    }
+
+    fn wait_modem_change(&mut self, lines: ModemLines) -> ::Result<ModemStatus> {
+        self.device.wait_modem_change(lines)
+    }
+
+    fn read_cts(&mut self) -> ::Result<bool> {
+        self.device.read_cts()
+    }
+
+    fn read_dsr(&mut self) -> ::Result<bool> {
+        self.device.read_dsr()
+    }
+
+    fn read_ri(&mut self) -> ::Result<bool> {
+        self.device.read_ri()
+    }
+
+    fn read_cd(&mut self) -> ::Result<bool> {
+        self.device.read_cd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::prelude::*;
+
+    use super::*;
+    use ::SerialPort;
+    use ::loopback::LoopbackPort;
+
+    fn loopback() -> Port<LoopbackPort> {
+        Port::new(LoopbackPort::new(), "loop".to_string()).unwrap()
+    }
+
+    #[test]
+    fn port_sets_individual_settings() {
+        let mut port = loopback();
+        port.set_baud_rate(::Baud115200).unwrap();
+        port.set_parity(::ParityEven).unwrap();
+
+        assert_eq!(port.baud_rate(), Some(::Baud115200));
+        assert_eq!(port.parity(), Some(::ParityEven));
+        assert_eq!(port.get_ref().read_settings().unwrap().baud_rate, ::Baud115200);
+    }
+
+    #[test]
+    fn port_tracks_settings_written_through_serial_port() {
+        let mut port = loopback();
+        port.reconfigure(&|settings| settings.set_baud_rate(::Baud19200)).unwrap();
+
+        assert_eq!(port.baud_rate(), Some(::Baud19200));
+    }
+
+    #[test]
+    fn port_forwards_io_to_device() {
+        let mut port = loopback();
+        port.write_all(b"ok").unwrap();
+
+        let mut buf = [0u8; 2];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ok");
+        assert_eq!(port.name(), "loop");
+    }
+}
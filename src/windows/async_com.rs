@@ -0,0 +1,429 @@
+use std::cmp;
+use std::ffi::OsStr;
+use std::io;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::Mutex;
+use std::task::{Context,Poll,Waker};
+
+use std::os::windows::prelude::*;
+
+use tokio::io::{AsyncRead,AsyncWrite,ReadBuf};
+
+use super::com::{COMPort,OpenOptions};
+use super::ffi::*;
+use super::overlapped::Overlapped;
+
+
+// the largest read or write that is handed to the driver at once
+const MAX_TRANSFER: usize = 64 * 1024;
+
+/// A COM port for asynchronous I/O with Tokio.
+///
+/// `AsyncCOMPort` is only available with the optional `async` feature. It implements Tokio's
+/// `AsyncRead` and `AsyncWrite` on a COM port that is opened for overlapped I/O, so a task that
+/// reads from or writes to the port doesn't block the thread it runs on.
+///
+/// Windows reports the completion of an overlapped read or write by signaling an event. The port
+/// registers a wait for the event with the Windows thread pool, which wakes the waiting task once
+/// the operation has completed, so `AsyncCOMPort` works with any Tokio runtime and doesn't need to
+/// be registered with the runtime's reactor.
+///
+/// A read completes as soon as at least one byte has been received, and a write completes once
+/// the driver has accepted the data. The timeouts of the underlying `COMPort` are replaced to
+/// achieve this, so reads and writes never time out. Use `tokio::time::timeout()` to bound them.
+///
+/// ```no_run,edition2018
+/// use tokio::io::{AsyncReadExt,AsyncWriteExt};
+/// use serial::windows::AsyncCOMPort;
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let mut port = AsyncCOMPort::open("COM1")?;
+///
+/// port.write_all(b"AT\r").await?;
+///
+/// let mut buf = [0u8; 64];
+/// let n = port.read(&mut buf).await?;
+/// println!("{:?}", &buf[..n]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncCOMPort {
+    // declared before the port, so that pending operations are cancelled before it's closed
+    read: Operation,
+    write: Operation,
+    port: COMPort
+}
+
+impl AsyncCOMPort {
+    /// Opens a COM port for asynchronous I/O.
+    ///
+    /// `port` should be the name of a COM port, e.g., `COM1`. The port is opened for overlapped
+    /// I/O, unlike `COMPort::open()`, which opens it for synchronous I/O.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn open<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<Self> {
+        AsyncCOMPort::open_with(port, &OpenOptions::new())
+    }
+
+    /// Opens a COM port for asynchronous I/O with additional options.
+    ///
+    /// The port is opened for overlapped I/O regardless of `OpenOptions::overlapped()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn open_with<T: AsRef<OsStr> + ?Sized>(port: &T, options: &OpenOptions) -> ::Result<Self> {
+        let mut options = *options;
+        AsyncCOMPort::new(try!(COMPort::open_with(port, options.overlapped(true))))
+    }
+
+    /// Converts a `COMPort` into an `AsyncCOMPort`.
+    ///
+    /// This can be used to configure the port with the methods of `COMPort` before using it
+    /// asynchronously. The port must have been opened with `OpenOptions::overlapped()`.
+    ///
+    /// ```no_run
+    /// use serial::prelude::*;
+    /// use serial::windows::{AsyncCOMPort,COMPort,OpenOptions};
+    ///
+    /// let mut port = COMPort::open_with("COM1", OpenOptions::new().overlapped(true)).unwrap();
+    ///
+    /// port.reconfigure(&|settings| {
+    ///     settings.set_baud_rate(serial::Baud115200)
+    /// }).unwrap();
+    ///
+    /// let port = AsyncCOMPort::new(port).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the port wasn't opened for overlapped I/O.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn new(port: COMPort) -> ::Result<Self> {
+        if !port.is_overlapped() {
+            return Err(::Error::new(0, ::ErrorKind::InvalidInput, "port wasn't opened for overlapped I/O"));
+        }
+
+        // ReadFile returns as soon as a byte is available and waits for one otherwise, up to the
+        // longest timeout that isn't infinite, after which the read is simply started again
+        let timeouts = COMMTIMEOUTS {
+            ReadIntervalTimeout: MAXDWORD,
+            ReadTotalTimeoutMultiplier: MAXDWORD,
+            ReadTotalTimeoutConstant: MAXDWORD - 1,
+            WriteTotalTimeoutMultiplier: 0,
+            WriteTotalTimeoutConstant: 0
+        };
+
+        if unsafe { SetCommTimeouts(port.as_raw_handle() as HANDLE, &timeouts) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        let handle = port.as_raw_handle() as HANDLE;
+
+        Ok(AsyncCOMPort {
+            read: try!(Operation::new(handle)),
+            write: try!(Operation::new(handle)),
+            port: port
+        })
+    }
+
+    /// Returns a reference to the underlying `COMPort`.
+    ///
+    /// The reference can be used to read the port's settings and modem status. The timeouts that
+    /// it reports don't apply to asynchronous reads and writes.
+    pub fn get_ref(&self) -> &COMPort {
+        &self.port
+    }
+}
+
+impl AsRawHandle for AsyncCOMPort {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.port.as_raw_handle()
+    }
+}
+
+impl AsyncRead for AsyncCOMPort {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            // a completed read may have received more than the caller's buffer could take
+            if this.read.start < this.read.end {
+                let len = cmp::min(buf.remaining(), this.read.end - this.read.start);
+                buf.put_slice(&this.read.buffer[this.read.start..this.read.start + len]);
+                this.read.start += len;
+
+                return Poll::Ready(Ok(()));
+            }
+
+            if !this.read.pending {
+                if buf.remaining() == 0 {
+                    return Poll::Ready(Ok(()));
+                }
+
+                if let Err(e) = this.read.start_read(cmp::min(buf.remaining(), MAX_TRANSFER)) {
+                    return Poll::Ready(Err(e));
+                }
+            }
+
+            match this.read.poll(cx) {
+                Poll::Ready(Ok(len)) => {
+                    // a read that timed out without data is started again
+                    this.read.start = 0;
+                    this.read.end = len;
+                },
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+    }
+}
+
+impl AsyncWrite for AsyncCOMPort {
+    /// Hands the data to the driver and returns without waiting for the write to complete.
+    ///
+    /// Only one write is in progress at a time, so a write that follows waits for the previous
+    /// one. An error of the previous write is returned by the next call to `poll_write()` or
+    /// `poll_flush()`.
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.port.is_read_only() {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::PermissionDenied, "port was opened read-only")));
+        }
+
+        if this.write.pending {
+            match this.write.poll(cx) {
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        let len = cmp::min(buf.len(), MAX_TRANSFER);
+        Poll::Ready(this.write.start_write(&buf[..len]).map(|()| len))
+    }
+
+    /// Waits until the driver has accepted the data of the last write.
+    ///
+    /// The data may still be in the driver's transmit queue when the flush completes.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.write.pending {
+            match this.write.poll(cx) {
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// A read or write that may still be in progress, along with the buffer that it uses.
+///
+/// The `OVERLAPPED` structure and the buffer must stay in place until the operation has
+/// completed, so both live on the heap, and an operation that is still pending when the
+/// `Operation` is dropped is cancelled first.
+struct Operation {
+    handle: HANDLE,
+    overlapped: Box<Overlapped>,
+    waker: Box<Mutex<Option<Waker>>>,
+    wait: HANDLE,
+    buffer: Vec<u8>,
+    pending: bool,
+    start: usize,
+    end: usize
+}
+
+// An Operation isn't Send or Sync by default only because of the raw pointers in its handles and
+// its OVERLAPPED. The handles can be used from any thread of the process. The OVERLAPPED and the
+// buffer are only used by the driver and through a mutable reference. The thread pool callback
+// runs on another thread, but it only uses the waker, which is protected by the mutex. A shared
+// reference gives access to nothing but the `pending` flag.
+unsafe impl Send for Operation {}
+unsafe impl Sync for Operation {}
+
+impl Operation {
+    fn new(handle: HANDLE) -> ::Result<Self> {
+        Ok(Operation {
+            handle: handle,
+            overlapped: Box::new(try!(Overlapped::new())),
+            waker: Box::new(Mutex::new(None)),
+            wait: ptr::null_mut(),
+            buffer: Vec::new(),
+            pending: false,
+            start: 0,
+            end: 0
+        })
+    }
+
+    fn start_read(&mut self, len: usize) -> io::Result<()> {
+        self.buffer.resize(len, 0);
+
+        let started = unsafe {
+            ReadFile(self.handle, self.buffer.as_mut_ptr() as LPVOID, len as DWORD, ptr::null_mut(), self.overlapped.as_mut_ptr())
+        };
+
+        self.started(started)
+    }
+
+    fn start_write(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(buf);
+
+        let started = unsafe {
+            WriteFile(self.handle, self.buffer.as_ptr() as LPVOID, buf.len() as DWORD, ptr::null_mut(), self.overlapped.as_mut_ptr())
+        };
+
+        self.started(started)
+    }
+
+    fn started(&mut self, started: BOOL) -> io::Result<()> {
+        // an operation that completes right away is reported through the OVERLAPPED as well
+        if started == 0 && unsafe { GetLastError() } != ERROR_IO_PENDING {
+            return Err(super::error::last_io_error());
+        }
+
+        self.pending = true;
+        Ok(())
+    }
+
+    // Returns the number of bytes transferred once the operation has completed. An operation that
+    // was aborted, e.g., by purging the driver's queues, transferred nothing.
+    fn poll(&mut self, cx: &mut Context) -> Poll<io::Result<usize>> {
+        // the waker is stored before checking, so a completion in between still wakes the task
+        if let Ok(mut waker) = self.waker.lock() {
+            *waker = Some(cx.waker().clone());
+        }
+
+        match self.overlapped.try_finish(self.handle, super::error::last_io_error) {
+            None => {
+                if self.wait.is_null() {
+                    if let Err(e) = self.register_wait() {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+
+                Poll::Pending
+            },
+            Some(result) => {
+                self.pending = false;
+                self.unregister_wait();
+
+                Poll::Ready(result.map(|len| len.unwrap_or(0) as usize))
+            }
+        }
+    }
+
+    fn register_wait(&mut self) -> io::Result<()> {
+        let context = &*self.waker as *const Mutex<Option<Waker>> as LPVOID;
+
+        let registered = unsafe {
+            RegisterWaitForSingleObject(&mut self.wait, self.overlapped.event(), wake, context, INFINITE, WT_EXECUTEONLYONCE)
+        };
+
+        match registered {
+            0 => {
+                self.wait = ptr::null_mut();
+                Err(io::Error::last_os_error())
+            },
+            _ => Ok(())
+        }
+    }
+
+    fn unregister_wait(&mut self) {
+        if !self.wait.is_null() {
+            // INVALID_HANDLE_VALUE waits for a callback that is running, which uses the waker
+            unsafe { UnregisterWaitEx(self.wait, INVALID_HANDLE_VALUE) };
+            self.wait = ptr::null_mut();
+        }
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if self.pending {
+            self.overlapped.cancel(self.handle);
+        }
+
+        self.unregister_wait();
+    }
+}
+
+impl ::std::fmt::Debug for Operation {
+    fn fmt(&self, fmt: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        fmt.debug_struct("Operation")
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+unsafe extern "system" fn wake(context: LPVOID, _timed_out: BOOLEAN) {
+    let waker = &*(context as *const Mutex<Option<Waker>>);
+
+    if let Ok(mut waker) = waker.lock() {
+        if let Some(waker) = waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::os::windows::prelude::*;
+
+    use tokio::io::{AsyncRead,AsyncWrite};
+
+    use super::*;
+
+    #[test]
+    fn async_com_port_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AsyncCOMPort>();
+    }
+
+    #[test]
+    fn async_com_port_is_async_read_and_write() {
+        fn assert_async_read_write<T: AsyncRead + AsyncWrite>() {}
+        assert_async_read_write::<AsyncCOMPort>();
+    }
+
+    #[test]
+    fn new_rejects_port_without_overlapped_io() {
+        let path = env::temp_dir().join("serial-rs-async-com-port-test");
+        let file = fs::File::create(&path).unwrap();
+
+        // the handle is closed when the rejected port is dropped
+        let port = unsafe { COMPort::from_raw_handle(file.into_raw_handle()) };
+        let err = AsyncCOMPort::new(port).unwrap_err();
+        assert_eq!(err.kind(), ::ErrorKind::InvalidInput);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
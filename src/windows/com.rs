@@ -1,33 +1,70 @@
 extern crate libc;
 
+use std::cmp;
 use std::ffi::OsStr;
+use std::fmt;
 use std::io;
-use std::mem;
+use std::process;
 use std::ptr;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize,Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration,Instant};
 
 use std::os::windows::prelude::*;
 
-use self::libc::c_void;
+use self::libc::{c_void,c_char};
 
 use super::ffi::*;
-use ::{SerialDevice,SerialPortSettings};
+use super::overlapped::Overlapped;
+use ::{SerialDevice,SerialPortSettings,PortSettings,ModemStatus,ModemLines};
 
 
 /// A serial port implementation for Windows COM ports.
 ///
-/// The port will be closed when the value is dropped.
+/// The port will be closed when the value is dropped. Output that hasn't been transmitted yet may
+/// be discarded when the port is closed, unless `set_drain_on_drop()` is enabled.
+///
+/// ## Thread Safety
+///
+/// `COMPort` is `Send` and `Sync`. Every method that takes `&self` either reads state that is
+/// cached in the `COMPort`, such as `timeout()` or `rts()`, or makes a query such as
+/// `read_settings()` that Windows allows on the same handle from several threads at once.
+/// Operations that change the port or perform I/O take `&mut self`, so they can't run
+/// concurrently through a shared reference.
+///
+/// To read and write from different threads at the same time, either share a `&COMPort`, which
+/// implements `Read` and `Write`, or use `try_clone()` to create a second `COMPort` for the same
+/// device and move it to the other thread. Windows serializes I/O on a COM port that isn't opened
+/// for overlapped I/O, though, so a read that is waiting for data holds up a write from another
+/// thread until the read returns. Full-duplex code should either open the port with
+/// `OpenOptions::overlapped()` or use a short read timeout so that writes aren't delayed for long.
 pub struct COMPort {
     handle: HANDLE,
-    timeout: Duration
+    name: String,
+    options: OpenOptions,
+    settings: Option<COMSettings>,
+    timeouts: Timeouts,
+    rs485: Option<Rs485Config>,
+    rs485_error: Option<io::Error>,
+    verify_baud_rate: bool,
+    drain_on_drop: bool,
+    flush_on_timeout: bool,
+    held_errors: AtomicUsize,
+    rts: bool,
+    dtr: bool
 }
 
 unsafe impl Send for COMPort {}
 
+// see the thread safety section of the COMPort documentation
+unsafe impl Sync for COMPort {}
+
 impl COMPort {
     /// Opens a COM port as a serial device.
     ///
-    /// `port` should be the name of a COM port, e.g., `COM1`.
+    /// `port` should be the name of a COM port, e.g., `COM1`. The name may also be given as a
+    /// device path such as `\\.\COM10`.
     ///
     /// ```no_run
     /// serial::windows::COMPort::open("COM1").unwrap();
@@ -35,397 +72,3186 @@ impl COMPort {
     ///
     /// ## Errors
     ///
-    /// * `NoDevice` if the device could not be opened. This could indicate that the device is
-    ///   already in use.
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
     /// * `InvalidInput` if `port` is not a valid device name.
     /// * `Io` for any other I/O error while opening or initializing the device.
     pub fn open<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<Self> {
-        let mut name = Vec::<u16>::new();
+        COMPort::open_with(port, &OpenOptions::new())
+    }
+
+    /// Opens a COM port as a serial device with additional options.
+    ///
+    /// `port` should be the name of a COM port, e.g., `COM1`. The `options` control how the port
+    /// is initialized immediately after it has been opened, before any other configuration.
+    ///
+    /// ```no_run
+    /// use serial::windows::{COMPort,OpenOptions};
+    ///
+    /// // keep DTR deasserted so the attached board doesn't reset
+    /// COMPort::open_with("COM1", OpenOptions::new().dtr(false)).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn open_with<T: AsRef<OsStr> + ?Sized>(port: &T, options: &OpenOptions) -> ::Result<Self> {
+        let handle = try!(create_file(port.as_ref(), options));
+        COMPort::from_handle(handle, port.as_ref(), options)
+    }
+
+    /// Opens a COM port for reading only.
+    ///
+    /// The port is opened without write access, which is enough to read its settings, timeouts,
+    /// and modem status, and to read data from it. Writing data or changing the settings of a
+    /// port that was opened read-only fails with `PermissionDenied`. This is equivalent to
+    /// `COMPort::open_with()` with `OpenOptions::read_only()` set.
+    ///
+    /// COM ports are opened for exclusive access unless `OpenOptions::shared()` is set, so whether
+    /// a read-only port can be opened while another process is using the device depends on the
+    /// driver.
+    ///
+    /// ```no_run
+    /// use serial::prelude::*;
+    ///
+    /// let port = serial::windows::COMPort::open_readonly("COM1").unwrap();
+    /// println!("{:?}", port.read_settings().unwrap().baud_rate());
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn open_readonly<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<Self> {
+        COMPort::open_with(port, OpenOptions::new().read_only(true))
+    }
+
+    /// Opens a COM port while doing as much as possible to avoid resetting the attached board.
+    ///
+    /// Boards such as Arduinos reset when DTR is asserted, and many drivers assert DTR, and
+    /// sometimes RTS, in `CreateFileW`, before any code can change it. This function deasserts DTR
+    /// and RTS as the very first operations on the new handle, and then writes settings whose DTR
+    /// and RTS control modes are `Disable`, unless a signal is used for handshaking. Later settings
+    /// written to the port should keep those modes, since switching back to `Enable` asserts the
+    /// signal again.
+    ///
+    /// Windows has no generic way to open a COM port without letting the driver set the control
+    /// signals first, so a pulse may remain. It lasts from the moment the driver opens the port
+    /// until the first `EscapeCommFunction()`, which is typically well under a millisecond for
+    /// built-in UARTs and a few milliseconds for USB adapters, and may still be enough to reset a
+    /// board. Because many drivers keep the control modes that were last written until the port
+    /// is opened again, only the first open after the device is plugged in usually pulses; later
+    /// opens through this function or with `OpenOptions::settings()` commonly don't.
+    ///
+    /// To check whether a port pulses DTR, connect it with a null modem cable to a second port,
+    /// which sees the first port's DTR as DSR. Open the second port, call
+    /// `wait_modem_change()` for DSR on it with a timeout of a second, and open the first port
+    /// with this function while it waits. A timeout means that DTR didn't change. A logic analyzer
+    /// on the DTR line shows the width of any pulse that remains.
+    ///
+    /// ## Errors
+    ///
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn open_no_reset<T: AsRef<OsStr> + ?Sized>(port: &T) -> ::Result<Self> {
+        let mut port = try!(COMPort::open_with(port, OpenOptions::new().dtr(false).rts(false)));
+
+        try!(port.reconfigure(|settings| {
+            if settings.dtr_mode() == DtrMode::Enable {
+                settings.set_dtr_mode(DtrMode::Disable);
+            }
+
+            if settings.rts_mode() == RtsMode::Enable {
+                settings.set_rts_mode(RtsMode::Disable);
+            }
+
+            Ok(())
+        }));
+
+        Ok(port)
+    }
+
+    /// Opens a COM port as a serial device, giving up if opening takes too long.
+    ///
+    /// Some USB serial drivers can block for a long time while a port is being opened. This
+    /// function opens the port on a separate thread and stops waiting for it after `timeout`. If
+    /// the port is opened after the timeout has expired, it is closed again.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// serial::windows::COMPort::open_timeout("COM1", Duration::from_secs(2)).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `Io(TimedOut)` if the port wasn't opened before the timeout expired.
+    /// * `NoDevice` if the device does not exist.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if `port` is not a valid device name.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn open_timeout<T: AsRef<OsStr> + ?Sized>(port: &T, timeout: Duration) -> ::Result<Self> {
+        // a rendezvous channel hands the handle over only while open_timeout() is still waiting
+        let (opened, receiver) = mpsc::sync_channel::<::Result<usize>>(0);
+        let name = port.as_ref().to_os_string();
+
+        thread::spawn(move || {
+            // handles aren't Send, so they cross threads as integers
+            let result = create_file(&name, &OpenOptions::new()).map(|handle| handle as usize);
+
+            if let Err(mpsc::SendError(Ok(handle))) = opened.send(result) {
+                unsafe { CloseHandle(handle as HANDLE) };
+            }
+        });
 
-        name.extend(OsStr::new("\\\\.\\").encode_wide());
-        name.extend(port.as_ref().encode_wide());
-        name.push(0);
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => COMPort::from_handle(try!(result) as HANDLE, port.as_ref(), &OpenOptions::new()),
+            Err(_) => Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"))
+        }
+    }
 
-        let handle = unsafe {
-            CreateFileW(name.as_ptr(), GENERIC_READ | GENERIC_WRITE, 0, ptr::null_mut(), OPEN_EXISTING, FILE_ATTRIBUTE_NORMAL, 0 as HANDLE)
+    fn from_handle(handle: HANDLE, name: &OsStr, options: &OpenOptions) -> ::Result<Self> {
+        let mut port = COMPort {
+            handle: handle,
+            name: name.to_string_lossy().into_owned(),
+            options: *options,
+            settings: options.settings,
+            timeouts: Timeouts::default(),
+            rs485: None,
+            rs485_error: None,
+            verify_baud_rate: true,
+            drain_on_drop: false,
+            flush_on_timeout: false,
+            held_errors: AtomicUsize::new(0),
+            rts: false,
+            dtr: false
         };
 
-        let timeout = Duration::from_millis(100);
+        try!(port.initialize());
+        Ok(port)
+    }
 
-        if handle != INVALID_HANDLE_VALUE {
-            let mut port = COMPort {
-                handle: handle,
-                timeout: timeout
+    /// Closes and reopens the COM port.
+    ///
+    /// This can be used to recover after a USB serial adapter has been unplugged and plugged back
+    /// in. The port is reopened by name with the same `OpenOptions`, and the settings most
+    /// recently written with `write_settings()`, the timeouts, the RS-485 configuration, and the
+    /// last levels of the RTS and DTR control signals are applied again. The level of a signal that
+    /// the settings hand to the driver for handshaking or toggling is left to the driver.
+    ///
+    /// ## Errors
+    ///
+    /// If the port can't be reopened and reinitialized, the port is left closed and every
+    /// operation other than `reopen()` fails until the port has been reopened successfully.
+    ///
+    /// * `NoDevice` if the device is absent.
+    /// * `Busy` if the device is already in use.
+    /// * `PermissionDenied` if the process isn't allowed to open the device.
+    /// * `InvalidInput` if a saved setting is no longer compatible with the device.
+    /// * `Io` for any other I/O error while opening or initializing the device.
+    pub fn reopen(&mut self) -> ::Result<()> {
+        let (rts, dtr) = (self.rts, self.dtr);
+
+        self.close_handle();
+        self.handle = try!(create_file(OsStr::new(&self.name), &self.options));
+
+        let result = self.initialize().and_then(|()| {
+            let settings = match self.settings {
+                Some(settings) => settings,
+                None => try!(self.read_settings())
             };
 
-            try!(port.set_timeout(timeout));
-            Ok(port)
+            self.restore_signal_levels(&settings, rts, dtr)
+        });
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.close_handle();
+                Err(e)
+            }
+        }
+    }
+
+    /// Closes the COM port and reports whether it was closed successfully.
+    ///
+    /// Dropping a `COMPort` closes it as well, but ignores any error. Closing a port that was left
+    /// closed by a failed `reopen()` succeeds without doing anything.
+    ///
+    /// ## Errors
+    ///
+    /// The port is closed even if an error is returned.
+    ///
+    /// * `Io(TimedOut)` if `set_drain_on_drop()` is enabled and the output wasn't transmitted
+    ///   before the write timeout expired.
+    /// * `Io` for any other I/O error while draining or closing the device.
+    pub fn close(mut self) -> ::Result<()> {
+        let drained = self.drain_before_close();
+        let handle = self.handle;
+
+        // the handle is released here either way, so Drop mustn't close it again
+        self.handle = INVALID_HANDLE_VALUE;
+
+        if handle == INVALID_HANDLE_VALUE || unsafe { CloseHandle(handle) } != 0 {
+            drained
         }
         else {
-            Err(super::error::last_os_error())
+            Err(super::error::last_device_error())
         }
     }
 
-    fn escape_comm_function(&mut self, function: DWORD) -> ::Result<()> {
-        match unsafe { EscapeCommFunction(self.handle, function) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(())
+    fn initialize(&mut self) -> ::Result<()> {
+        let settings = try!(self.read_settings());
+        self.update_signal_levels(&settings);
+
+        // reopen() applies the settings that were written last, open_with() those in the options
+        if let Some(settings) = self.settings {
+            try!(self.set_comm_state(&settings));
+        }
+
+        if let Some(level) = self.options.dtr {
+            try!(self.set_dtr(level));
+        }
+
+        if let Some(level) = self.options.rts {
+            try!(self.set_rts(level));
+        }
+
+        if let Some(config) = self.rs485 {
+            try!(self.set_rts(!config.rts_on_send));
+        }
+
+        let timeouts = self.timeouts;
+        self.apply_timeouts(&timeouts)
+    }
+
+    fn close_handle(&mut self) {
+        if self.handle != INVALID_HANDLE_VALUE {
+            unsafe {
+                CloseHandle(self.handle);
+            }
+
+            self.handle = INVALID_HANDLE_VALUE;
         }
     }
 
-    fn read_pin(&mut self, pin: DWORD) -> ::Result<bool> {
-        let mut status: DWORD = unsafe { mem::uninitialized() };
+    /// Returns the level that the RTS control signal was last set to.
+    ///
+    /// Windows can't read back the state of output control signals, so this is the level that
+    /// was last set with `set_rts()` or by writing settings that enable or disable RTS. The level
+    /// is restored by `reopen()`. While RTS is under handshaking or toggle control, the level is
+    /// managed by the driver and may differ from the returned value.
+    pub fn rts(&self) -> bool {
+        self.rts
+    }
 
-        match unsafe { GetCommModemStatus(self.handle, &mut status) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(status & pin != 0)
+    /// Returns the level that the DTR control signal was last set to.
+    ///
+    /// Windows can't read back the state of output control signals, so this is the level that
+    /// was last set with `set_dtr()` or by writing settings that enable or disable DTR. The level
+    /// is restored by `reopen()`. While DTR is under handshaking control, the level is managed by
+    /// the driver and may differ from the returned value.
+    pub fn dtr(&self) -> bool {
+        self.dtr
+    }
+
+    // SetCommState changes the control signals according to their control modes
+    fn update_signal_levels(&mut self, settings: &COMSettings) {
+        match settings.inner.fBits & fRtsControl {
+            RTS_CONTROL_ENABLE => self.rts = true,
+            RTS_CONTROL_DISABLE => self.rts = false,
+            _ => ()
+        }
+
+        match settings.inner.fBits & fDtrControl {
+            DTR_CONTROL_ENABLE => self.dtr = true,
+            DTR_CONTROL_DISABLE => self.dtr = false,
+            _ => ()
+        }
+    }
+
+    // EscapeCommFunction fails for a signal that the driver controls for handshaking or toggling,
+    // so only the levels of signals that are enabled or disabled are set
+    fn restore_signal_levels(&mut self, settings: &COMSettings, rts: bool, dtr: bool) -> ::Result<()> {
+        match settings.inner.fBits & fRtsControl {
+            RTS_CONTROL_ENABLE | RTS_CONTROL_DISABLE => try!(self.set_rts(rts)),
+            _ => self.rts = rts
+        }
+
+        match settings.inner.fBits & fDtrControl {
+            DTR_CONTROL_ENABLE | DTR_CONTROL_DISABLE => try!(self.set_dtr(dtr)),
+            _ => self.dtr = dtr
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of the COM port, e.g., `COM1`.
+    ///
+    /// This is the name that was passed to `open()`, which can be passed to `open()` again to open
+    /// the same port.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn is_overlapped(&self) -> bool {
+        self.options.overlapped
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.options.read_only
+    }
+
+    /// Creates a new handle to the same COM port.
+    ///
+    /// The underlying handle is duplicated, so both `COMPort` values refer to the same open
+    /// device and the device remains open until every clone has been dropped. This can be used to
+    /// read from the port on one thread while writing to it from another.
+    ///
+    /// The timeouts and settings belong to the device, not the handle, so changing them through
+    /// one clone affects all clones. If more than one clone reads from the port at the same time,
+    /// the order in which the incoming bytes are distributed between them is undefined.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other error while duplicating the handle.
+    pub fn try_clone(&self) -> ::Result<Self> {
+        // INVALID_HANDLE_VALUE doubles as the pseudo handle of the current process
+        if self.handle == INVALID_HANDLE_VALUE {
+            return Err(::Error::new(ERROR_INVALID_HANDLE as i32, ::ErrorKind::Disconnected, "port is closed"));
+        }
+
+        let mut handle: HANDLE = INVALID_HANDLE_VALUE;
+
+        let res = unsafe {
+            let process = GetCurrentProcess();
+            DuplicateHandle(process, self.handle, process, &mut handle, 0, 0, DUPLICATE_SAME_ACCESS)
+        };
+
+        match res {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(COMPort {
+                handle: handle,
+                name: self.name.clone(),
+                options: self.options,
+                settings: self.settings,
+                timeouts: self.timeouts,
+                rs485: self.rs485,
+                rs485_error: None,
+                verify_baud_rate: self.verify_baud_rate,
+                drain_on_drop: self.drain_on_drop,
+                flush_on_timeout: self.flush_on_timeout,
+                held_errors: AtomicUsize::new(0),
+                rts: self.rts,
+                dtr: self.dtr
+            })
+        }
+    }
+
+    fn apply_timeouts(&mut self, timeouts: &Timeouts) -> ::Result<()> {
+        match unsafe { SetCommTimeouts(self.handle, &timeouts.to_comm_timeouts()) } {
+            0 => Err(super::error::last_device_error()),
+            _ => {
+                self.timeouts = *timeouts;
+                Ok(())
+            }
+        }
+    }
+
+    fn escape_comm_function(&mut self, function: DWORD) -> ::Result<()> {
+        match unsafe { EscapeCommFunction(self.handle, function) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
         }
     }
 
     /// Primative method for pulling information about device driver
     /// For full details see: https://msdn.microsoft.com/en-us/library/windows/desktop/aa363189%28v=vs.85%29.aspx
+    #[deprecated(note = "use `comm_properties()` instead")]
     pub fn driver_properties(&mut self) -> ::Result<Box<LPCOMMPROP>> {
-        let b = Box::new(LPCOMMPROP::default());
-        let ptr: *mut LPCOMMPROP = unsafe{ mem::transmute(b)};
-        match unsafe{ GetCommProperties( self.handle, ptr) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(unsafe{mem::transmute(ptr)})
+        self.raw_comm_properties().map(Box::new)
+    }
+
+    /// Returns the capabilities and queue sizes reported by the driver.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn comm_properties(&mut self) -> ::Result<CommProperties> {
+        self.raw_comm_properties().map(|props| CommProperties::from_raw(&props))
+    }
+
+    /// Returns the baud rates and parameters that the port supports.
+    ///
+    /// This can be used to offer only the settings that the hardware supports, rather than
+    /// having `write_settings()` reject them.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn capabilities(&mut self) -> ::Result<Capabilities> {
+        self.raw_comm_properties().map(|props| Capabilities::from_raw(&props))
+    }
+
+    fn raw_comm_properties(&mut self) -> ::Result<LPCOMMPROP> {
+        let mut props = LPCOMMPROP::default();
+
+        match unsafe { GetCommProperties(self.handle, &mut props) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(props)
         }
     }
 
     /// Get # of bytes in RX_Buffer
     pub fn tx_buffer(&mut self) -> ::Result<u32> {
-        match self.driver_properties()  {
-            Err(e) => Err(e),
-            Ok(x) => Ok(x.dwCurrentTxQueue)
-        }
+        self.comm_properties().map(|props| props.current_tx_queue)
     }
 
     /// Get # of bytes in RX Buffer
     pub fn rx_buffer(&mut self) -> ::Result<u32> {
-        match self.driver_properties() {
-            Err(e) => Err(e),
-            Ok(x) => Ok(x.dwCurrentRxQueue)
+        self.comm_properties().map(|props| props.current_rx_queue)
+    }
+
+    /// Requests new sizes for the driver's receive and transmit queues, in bytes.
+    ///
+    /// The driver is free to round the sizes or to ignore the request entirely. The resulting
+    /// queue sizes can be checked with `comm_properties()`, whose `current_rx_queue` and
+    /// `current_tx_queue` fields are also returned by `rx_buffer()` and `tx_buffer()`.
+    pub fn set_buffer_sizes(&mut self, rx: u32, tx: u32) -> ::Result<()> {
+        match unsafe { SetupComm(self.handle, rx as DWORD, tx as DWORD) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
+        }
+    }
+
+    /// Selects the events that are reported by `wait_event()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_event_mask(&mut self, mask: EventMask) -> ::Result<()> {
+        match unsafe { SetCommMask(self.handle, mask.to_bits()) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
+        }
+    }
+
+    /// Blocks until one of the events selected with `set_event_mask()` occurs.
+    ///
+    /// The returned `EventMask` indicates which of the selected events occurred. The wait is
+    /// bounded by the read timeout set with `set_timeout()`. A timeout of zero checks for events
+    /// once without blocking.
+    ///
+    /// A port opened with `OpenOptions::overlapped()` waits with `WaitCommEvent`. If the event
+    /// mask is changed while the wait is in progress, the wait ends with an empty `EventMask`.
+    /// Windows can't cancel a `WaitCommEvent` on other ports, so they poll the driver about once
+    /// per millisecond until the timeout expires: `rx_char` reports characters that arrive during
+    /// the wait, the modem events report changes of their signals, and `line_break` and `error`
+    /// report line errors that haven't been taken by `line_errors()` yet.
+    ///
+    /// ## Errors
+    ///
+    /// * `Io(TimedOut)` if none of the selected events occurred before the timeout expired.
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn wait_event(&mut self) -> ::Result<EventMask> {
+        let timeout = self.timeouts.read;
+        self.wait_comm_event(timeout)
+    }
+
+    // a timeout of zero checks once, since WaitForSingleObject doesn't block for zero milliseconds
+    // and the polling loop compares the driver's status only once before the deadline has passed
+    fn wait_comm_event(&self, timeout: Duration) -> ::Result<EventMask> {
+        if !self.options.overlapped {
+            return self.poll_comm_event(timeout);
+        }
+
+        let mut mask: DWORD = 0;
+        let mut overlapped = try!(Overlapped::new());
+        let started = unsafe { WaitCommEvent(self.handle, &mut mask, overlapped.as_mut_ptr()) };
+
+        match try!(overlapped.finish(self.handle, started, Some(duration_to_millis(timeout)), super::error::last_device_error)) {
+            Some(_) => Ok(EventMask::from_bits(mask)),
+            None => Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"))
+        }
+    }
+
+    // WaitCommEvent can only be cancelled on an overlapped handle, so other handles derive the
+    // events from the driver's status until the deadline
+    fn poll_comm_event(&self, timeout: Duration) -> ::Result<EventMask> {
+        let mut mask: DWORD = 0;
+
+        if unsafe { GetCommMask(self.handle, &mut mask) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        let start = Instant::now();
+        let mut previous = try!(self.polled_status());
+
+        loop {
+            let status = try!(self.polled_status());
+            let events = status.events_since(&previous) & mask;
+
+            if events != 0 {
+                return Ok(EventMask::from_bits(events));
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"));
+            }
+
+            previous = status;
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    // a WaitCommEvent for EV_RXCHAR couldn't be cancelled at the deadline on a handle without
+    // overlapped I/O, so the receive queue is checked until data arrives or the deadline passes
+    fn poll_readable(&self, timeout: Duration) -> ::Result<bool> {
+        let start = Instant::now();
+
+        loop {
+            if try!(self.clear_comm_error()).cbInQue > 0 {
+                return Ok(true);
+            }
+
+            if start.elapsed() >= timeout {
+                return Ok(false);
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn polled_status(&self) -> ::Result<PolledStatus> {
+        let comstat = try!(self.clear_comm_error());
+        let mut modem: DWORD = 0;
+
+        if unsafe { GetCommModemStatus(self.handle, &mut modem) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        Ok(PolledStatus {
+            bytes_in: comstat.cbInQue,
+            modem: modem,
+            errors: self.held_errors.load(Ordering::SeqCst) as DWORD
+        })
+    }
+
+    /// Enables or disables RS-485 transceiver control.
+    ///
+    /// Windows has no kernel support for RS-485, so transceiver control is emulated with the RTS
+    /// control signal. While enabled, every write sets RTS to the transmit level, waits for
+    /// `delay_before_send`, writes the data and waits for it to drain, waits for
+    /// `delay_after_send`, and then returns RTS to the receive level. RTS is set to the receive
+    /// level as soon as RS-485 control is enabled. Passing `None` disables RS-485 control and
+    /// leaves RTS in its current state.
+    ///
+    /// Because draining can only observe the driver's transmit queue, the last bytes may still be
+    /// leaving the UART's hardware FIFO when the drain completes. `delay_after_send` can be used to
+    /// cover that time.
+    ///
+    /// A write that sent data reports the number of bytes written even if draining the data or
+    /// returning RTS to the receive level fails afterwards. That failure is returned by the next
+    /// write instead, before any data is sent. Calling `set_rs485()` discards such a failure.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn set_rs485(&mut self, config: Option<Rs485Config>) -> ::Result<()> {
+        if let Some(ref config) = config {
+            try!(self.set_rts(!config.rts_on_send));
         }
+
+        self.rs485 = config;
+        self.rs485_error = None;
+        Ok(())
+    }
+
+    /// Returns the current RS-485 transceiver configuration, if enabled.
+    pub fn rs485(&self) -> Option<Rs485Config> {
+        self.rs485
     }
 
     /// Purge operations
     pub fn purge_rxabort(&mut self) -> ::Result<()> {
         match unsafe{ PurgeComm(self.handle, PURGE_RXABORT ) } {
-            0 => Err(super::error::last_os_error()),
+            0 => Err(super::error::last_device_error()),
             _ => Ok(())
         }
     }
-    pub fn purge_rxclear(&mut self) -> ::Result<()> {
-        match unsafe{ PurgeComm(self.handle, PURGE_RXCLEAR ) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(())
+    pub fn purge_rxclear(&mut self) -> ::Result<()> {
+        match unsafe{ PurgeComm(self.handle, PURGE_RXCLEAR ) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
+        }
+    }
+    pub fn purge_txabort(&mut self) -> ::Result<()> {
+        match unsafe{ PurgeComm(self.handle, PURGE_TXABORT ) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
+        }
+    }
+    pub fn purge_txclear(&mut self) -> ::Result<()> {
+        match unsafe{ PurgeComm(self.handle, PURGE_TXCLEAR ) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
+        }
+    }
+
+    /// Discards pending output and waits for the character being transmitted to finish.
+    ///
+    /// Unlike `clear(ClearBuffer::Output)`, which discards the output queue and returns right
+    /// away, this also aborts a write in progress and then waits until the driver's queue is empty
+    /// and the character in the UART's transmitter has had time to be sent. When it returns, the
+    /// line is idle, so the next write starts on a character boundary.
+    ///
+    /// The wait for the queue to empty is bounded by the write timeout set with
+    /// `set_write_timeout()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io(TimedOut)` if the queue didn't empty before the write timeout expired.
+    /// * `Io` for any other type of I/O error.
+    pub fn abort_output(&mut self) -> ::Result<()> {
+        if unsafe { PurgeComm(self.handle, PURGE_TXABORT | PURGE_TXCLEAR) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        try!(self.drain());
+
+        // Windows doesn't report when the transmit shift register is empty, so allow one
+        // character time for it to finish
+        let settings = try!(self.read_settings());
+        thread::sleep(settings.frame_duration().unwrap_or(Duration::from_millis(1)));
+        Ok(())
+    }
+
+    /// status operations
+    pub fn bytes_in(&mut self) -> ::Result<u64> {
+        SerialDevice::bytes_available(self).map(|n| n as u64)
+    }
+    /// status operations
+    pub fn bytes_out(&mut self) -> ::Result<u64> {
+        Ok(try!(self.clear_comm_error()).cbOutQue as u64)
+    }
+
+    /// Returns the number of bytes in both driver queues and the pending line errors.
+    ///
+    /// All of the status is read with a single call to the driver, so polling both queues costs
+    /// no more than `bytes_in()` or `bytes_out()` alone. Like `line_errors()`, this clears the
+    /// line errors that it reports.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn queue_status(&mut self) -> ::Result<QueueStatus> {
+        let comstat = try!(self.clear_comm_error());
+
+        Ok(QueueStatus {
+            bytes_in: comstat.cbInQue as u64,
+            bytes_out: comstat.cbOutQue as u64,
+            errors: LineErrors::from_bits(self.take_errors())
+        })
+    }
+
+    /// Reads and clears the line errors reported by the driver.
+    ///
+    /// The driver records an error condition when it occurs and holds it until it is cleared, so
+    /// the returned `LineErrors` describes every error since the previous call. A single call both
+    /// reads and clears the conditions, and `queue_status()` clears them as well. Other calls that
+    /// query the driver's queues, such as `bytes_in()` or a polled `wait_event()`, acknowledge the
+    /// errors in the driver but keep them for the next call to `line_errors()`.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn line_errors(&mut self) -> ::Result<LineErrors> {
+        self.clear_errors()
+    }
+
+    /// Acknowledges and clears the line errors reported by the driver.
+    ///
+    /// When the driver is configured to abort on errors, which some drivers are by default, a line
+    /// error such as a break or a framing error makes every following read and write fail until
+    /// the error has been acknowledged. Calling `clear_errors()` acknowledges the error, so the port
+    /// is ready for I/O again, and returns the errors that were cleared. Until then, no further
+    /// data is received, which can look like the device has stopped sending.
+    ///
+    /// This performs the same call to the driver as `line_errors()`; the two differ only in intent.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn clear_errors(&mut self) -> ::Result<LineErrors> {
+        try!(self.clear_comm_error());
+        Ok(LineErrors::from_bits(self.take_errors()))
+    }
+
+    // Polled in tight loops, so the driver fills in a COMSTAT on the stack rather than the heap.
+    // ClearCommError clears the errors that it reports, so they're held until taken.
+    pub(crate) fn clear_comm_error(&self) -> ::Result<COMSTAT> {
+        let mut errors: DWORD = 0;
+        let mut comstat = COMSTAT::default();
+
+        match unsafe { ClearCommError(self.handle, &mut errors, &mut comstat) } {
+            0 => Err(super::error::last_device_error()),
+            _ => {
+                self.held_errors.fetch_or(errors as usize, Ordering::SeqCst);
+                Ok(comstat)
+            }
+        }
+    }
+
+    fn take_errors(&self) -> DWORD {
+        self.held_errors.swap(0, Ordering::SeqCst) as DWORD
+    }
+
+    /// Enables or disables verification of non-standard baud rates.
+    ///
+    /// When enabled, which is the default, writing settings with a `BaudOther` baud rate reads the
+    /// settings back from the driver and fails with `InvalidInput` if the driver didn't apply the
+    /// exact rate. Verification can be disabled for drivers that apply the requested rate but
+    /// report a rounded value.
+    pub fn set_verify_baud_rate(&mut self, verify: bool) {
+        self.verify_baud_rate = verify;
+    }
+
+    /// Enables or disables draining the output before the port is closed.
+    ///
+    /// Some drivers discard output that hasn't been transmitted when the handle is closed, so the
+    /// last write before dropping the port can be lost. When enabled, dropping the port or calling
+    /// `close()` first waits for the output to be transmitted, as with `drain()`. The wait is
+    /// bounded by the write timeout; with a write timeout of zero, it lasts until the output has
+    /// been transmitted, which can be forever if flow control holds it back.
+    ///
+    /// Draining is disabled by default. Errors while draining on drop are ignored.
+    pub fn set_drain_on_drop(&mut self, drain: bool) {
+        self.drain_on_drop = drain;
+    }
+
+    /// Enables or disables discarding the input buffer when a read times out.
+    ///
+    /// When a frame is cut short, the bytes of the frame that arrive after the read timed out
+    /// stay in the input buffer and are returned at the start of the next read, where they
+    /// corrupt the next frame. When enabled, every read that fails with `TimedOut` purges the
+    /// driver's input buffer, as with `purge_rxclear()`, so the next read starts with fresh data.
+    ///
+    /// This discards all buffered input on every timeout, including data that arrived between the
+    /// timeout and the purge. It's disabled by default. Reads that return some data before timing
+    /// out don't fail, so they don't purge the buffer.
+    pub fn set_flush_on_timeout(&mut self, flush: bool) {
+        self.flush_on_timeout = flush;
+    }
+
+    fn drain_before_close(&mut self) -> ::Result<()> {
+        if self.drain_on_drop && self.handle != INVALID_HANDLE_VALUE && !self.options.read_only {
+            self.drain()
+        }
+        else {
+            Ok(())
+        }
+    }
+
+    /// Reads the port's current baud rate from the driver.
+    ///
+    /// This is a shortcut for reading the settings with `read_settings()` and calling
+    /// `baud_rate()` on them, as are `char_size()`, `parity()`, `stop_bits()`, and
+    /// `flow_control()`. Each returns `None` if the driver reports a value that the crate doesn't
+    /// model.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn baud_rate(&self) -> ::Result<Option<::BaudRate>> {
+        self.read_settings().map(|settings| settings.baud_rate())
+    }
+
+    /// Reads the port's current character size from the driver.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn char_size(&self) -> ::Result<Option<::CharSize>> {
+        self.read_settings().map(|settings| settings.char_size())
+    }
+
+    /// Reads the port's current parity-checking mode from the driver.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn parity(&self) -> ::Result<Option<::Parity>> {
+        self.read_settings().map(|settings| settings.parity())
+    }
+
+    /// Reads the port's current number of stop bits from the driver.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn stop_bits(&self) -> ::Result<Option<::StopBits>> {
+        self.read_settings().map(|settings| settings.stop_bits())
+    }
+
+    /// Reads the port's current flow control mode from the driver.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn flow_control(&self) -> ::Result<Option<::FlowControl>> {
+        self.read_settings().map(|settings| settings.flow_control())
+    }
+
+    /// Returns how far the baud rate used by the driver is from the requested baud rate.
+    ///
+    /// The error is a percentage of the requested rate, compared with the baud rate most recently
+    /// written with `write_settings()`. It's negative if the driver uses a slower rate. For
+    /// example, a UART that runs at 248000 baud when 250000 baud was requested has an error of
+    /// about -0.8%. Communication usually becomes unreliable once the combined error of both ends
+    /// exceeds a few percent.
+    ///
+    /// Returns `None` if no settings have been written, the requested rate was zero, or the
+    /// driver's settings can't be read. Many drivers report the requested rate even if the
+    /// hardware can't produce it exactly, so an error of zero only means the driver didn't report
+    /// a different rate. Disable baud rate verification with `set_verify_baud_rate()` to write a
+    /// non-standard rate that the driver adjusts.
+    pub fn baud_error(&self) -> Option<f64> {
+        let requested = match self.settings {
+            Some(settings) => settings.inner.BaudRate,
+            None => return None
+        };
+
+        self.read_settings().ok().and_then(|applied| baud_error(requested, applied.inner.BaudRate))
+    }
+
+    /// Alters the port's settings with a closure.
+    ///
+    /// The current settings are read from the device and passed to `setup`. If `setup` returns
+    /// successfully, the modified settings are written back to the device. Unlike
+    /// `SerialPort::reconfigure()`, the closure receives the `COMSettings` itself, so settings that
+    /// are specific to COM ports can be changed as well.
+    ///
+    /// ```no_run
+    /// use serial::prelude::*;
+    ///
+    /// let mut port = serial::windows::COMPort::open("COM1").unwrap();
+    ///
+    /// port.reconfigure(|settings| {
+    ///     try!(settings.set_baud_rate(serial::Baud115200));
+    ///     settings.set_parity(serial::ParityNone);
+    ///     settings.set_flow_control_chars(0x11, 0x13);
+    ///     Ok(())
+    /// }).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
+    /// * `Io` for any other type of I/O error.
+    /// * Any error returned by `setup`.
+    pub fn reconfigure<F: FnOnce(&mut COMSettings) -> ::Result<()>>(&mut self, setup: F) -> ::Result<()> {
+        let mut settings = try!(self.read_settings());
+        try!(setup(&mut settings));
+        self.write_settings(&settings)
+    }
+
+    /// Applies settings to the port, restoring the previous settings if they aren't accepted.
+    ///
+    /// The settings are read back after they have been written to confirm that the driver applied
+    /// the baud rate, character size, parity, stop bits, and flow control as requested. If the
+    /// settings are rejected or the driver changed any of them, the settings that were active
+    /// before the call are written back, so a failed call leaves the port configured as it was.
+    /// The levels of RTS and DTR returned by `rts()` and `dtr()` are restored as well.
+    ///
+    /// ## Errors
+    ///
+    /// If the previous settings can't be written back either, the error's description says so
+    /// after describing the original error.
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `InvalidInput` if a setting is not compatible with the underlying hardware.
+    /// * `Io` for any other type of I/O error.
+    pub fn apply(&mut self, settings: &COMSettings) -> ::Result<()> {
+        let snapshot = try!(self.read_settings());
+        let cached = (self.settings, self.rts, self.dtr);
+
+        let result = self.set_comm_state(settings).and_then(|()| {
+            let applied = try!(self.read_settings());
+
+            if applied == *settings {
+                Ok(())
+            }
+            else {
+                Err(::Error::new(0, ::ErrorKind::InvalidInput, "settings were not applied by the device"))
+            }
+        });
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                let (settings, rts, dtr) = cached;
+                self.settings = settings;
+                self.rts = rts;
+                self.dtr = dtr;
+
+                if unsafe { SetCommState(self.handle, &snapshot.inner) } == 0 {
+                    // the original error is reported first, since it's the one the caller can act on
+                    let restore = super::error::last_device_error();
+                    let description = format!("{}; restoring the previous settings failed: {}", e, restore);
+
+                    return Err(::Error::new(e.raw_os_error().unwrap_or(0), e.kind(), description));
+                }
+
+                // writing the snapshot sets the signals whose control modes enable or disable them
+                self.update_signal_levels(&snapshot);
+                Err(e)
+            }
+        }
+    }
+
+    /// Takes a snapshot of the port's complete configuration.
+    ///
+    /// The snapshot holds the driver's full device control block and timeouts, including fields
+    /// that `COMSettings` doesn't expose, along with the levels of RTS and DTR. Passing it to
+    /// `restore_state()` puts the port back exactly as it was, which lets a tool reconfigure a
+    /// port temporarily and leave it as it found it.
+    ///
+    /// ```no_run
+    /// let mut port = serial::windows::COMPort::open("COM1").unwrap();
+    /// let state = port.save_state().unwrap();
+    ///
+    /// // ... reconfigure and use the port ...
+    ///
+    /// port.restore_state(&state).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn save_state(&self) -> ::Result<PortState> {
+        let settings = try!(self.read_settings());
+        let mut comm_timeouts = Timeouts::default().to_comm_timeouts();
+
+        if unsafe { GetCommTimeouts(self.handle, &mut comm_timeouts) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        Ok(PortState {
+            settings: settings,
+            comm_timeouts: comm_timeouts,
+            timeouts: self.timeouts,
+            rts: self.rts,
+            dtr: self.dtr
+        })
+    }
+
+    /// Restores a configuration that was saved with `save_state()`.
+    ///
+    /// The device control block and timeouts are written back unchanged, and RTS and DTR are
+    /// returned to their saved levels. The level of a signal that the saved settings hand to the
+    /// driver for handshaking or toggling is left to the driver.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if the device was disconnected.
+    /// * `PermissionDenied` if the port was opened read-only.
+    /// * `Io` for any other type of I/O error.
+    pub fn restore_state(&mut self, state: &PortState) -> ::Result<()> {
+        try!(self.set_comm_state(&state.settings));
+
+        if unsafe { SetCommTimeouts(self.handle, &state.comm_timeouts) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        self.timeouts = state.timeouts;
+
+        self.restore_signal_levels(&state.settings, state.rts, state.dtr)
+    }
+
+    /// write settings
+    pub fn write_settings(&mut self, settings: &COMSettings) -> ::Result<()> {
+        self.set_comm_state(settings)
+    }
+
+    fn set_comm_state(&mut self, settings: &COMSettings) -> ::Result<()> {
+        if self.options.read_only {
+            return Err(::Error::new(0, ::ErrorKind::PermissionDenied, "port was opened read-only"));
+        }
+
+        if unsafe { SetCommState(self.handle, &settings.inner) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        // Some drivers accept any baud rate but silently substitute the closest one they support.
+        // Standard baud rates are assumed to be supported.
+        if let Some(::BaudOther(_)) = settings.baud_rate() {
+            if self.verify_baud_rate {
+                let applied = try!(self.read_settings());
+
+                if applied.inner.BaudRate != settings.inner.BaudRate {
+                    return Err(::Error::new(0, ::ErrorKind::InvalidInput, format!("baud rate {} is not supported by the device", settings.inner.BaudRate)));
+                }
+            }
+        }
+
+        self.update_signal_levels(settings);
+        self.settings = Some(*settings);
+        Ok(())
+    }
+}
+
+impl Drop for COMPort {
+    fn drop(&mut self) {
+        let _ = self.drain_before_close();
+        self.close_handle();
+    }
+}
+
+impl fmt::Debug for COMPort {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("COMPort")
+            .field("name", &self.name)
+            .field("handle", &self.handle)
+            .field("timeout", &self.timeouts.read)
+            .field("write_timeout", &self.timeouts.write)
+            .field("nonblocking", &self.timeouts.nonblocking)
+            .field("rs485", &self.rs485)
+            .field("verify_baud_rate", &self.verify_baud_rate)
+            .field("drain_on_drop", &self.drain_on_drop)
+            .field("flush_on_timeout", &self.flush_on_timeout)
+            .finish()
+    }
+}
+
+impl AsRawHandle for COMPort {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.handle as RawHandle
+    }
+}
+
+impl IntoRawHandle for COMPort {
+    /// Consumes the port and returns its handle without closing it.
+    fn into_raw_handle(mut self) -> RawHandle {
+        let handle = self.handle;
+        self.handle = INVALID_HANDLE_VALUE;
+        handle as RawHandle
+    }
+}
+
+impl From<COMPort> for process::Stdio {
+    /// Converts the port into a standard stream for a child process.
+    ///
+    /// The child inherits the port's handle along with its current settings and timeouts. Most
+    /// programs treat a read that returns no data as the end of the stream, so the port should
+    /// be set to `TimeoutMode::None` first unless the child is prepared for read timeouts.
+    ///
+    /// ```no_run
+    /// use std::process::Command;
+    /// use serial::SerialPort;
+    ///
+    /// let mut port = serial::windows::COMPort::open("COM1").unwrap();
+    /// port.set_timeout_mode(serial::TimeoutMode::None).unwrap();
+    /// let output = port.try_clone().unwrap();
+    ///
+    /// let child = Command::new("cmd.exe")
+    ///     .stdin(port)
+    ///     .stdout(output)
+    ///     .spawn()
+    ///     .unwrap();
+    /// ```
+    fn from(port: COMPort) -> process::Stdio {
+        unsafe { process::Stdio::from_raw_handle(port.into_raw_handle()) }
+    }
+}
+
+impl FromRawHandle for COMPort {
+    /// Creates a `COMPort` from a handle to an open COM port.
+    ///
+    /// The port takes ownership of the handle and closes it when dropped. Its timeouts are reset
+    /// to the defaults used by `open()`. Because the name of the port isn't known, `name()`
+    /// returns an empty string and `reopen()` fails. It also isn't known whether the handle was
+    /// opened for overlapped I/O, so `wait_event()` polls the driver as it does for a port that
+    /// wasn't.
+    unsafe fn from_raw_handle(handle: RawHandle) -> Self {
+        let mut port = COMPort {
+            handle: handle as HANDLE,
+            name: String::new(),
+            options: OpenOptions::new(),
+            settings: None,
+            timeouts: Timeouts::default(),
+            rs485: None,
+            rs485_error: None,
+            verify_baud_rate: true,
+            drain_on_drop: false,
+            flush_on_timeout: false,
+            held_errors: AtomicUsize::new(0),
+            rts: false,
+            dtr: false
+        };
+
+        if let Ok(settings) = port.read_settings() {
+            port.update_signal_levels(&settings);
+        }
+
+        // the handle may belong to a device that doesn't support timeouts, which isn't fatal
+        let timeouts = port.timeouts;
+        let _ = port.apply_timeouts(&timeouts);
+
+        port
+    }
+}
+
+impl io::Read for COMPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_file(buf)
+    }
+}
+
+impl io::Write for COMPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[io::IoSlice::new(buf)])
+    }
+
+    /// Writes the buffers one after another with a `WriteFile` call each, stopping at the first
+    /// buffer that isn't written completely. With RS-485 control enabled, the transceiver is
+    /// switched to transmit once for all of the buffers.
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        if self.options.read_only {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "port was opened read-only"));
+        }
+
+        match self.rs485 {
+            Some(config) => self.write_rs485(bufs, &config),
+            None => self.write_file_vectored(bufs)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_file()
+    }
+}
+
+/// Reads from a shared reference to the port, which allows reading on one thread while another
+/// thread writes. See the thread safety section of the `COMPort` documentation.
+impl io::Read for &COMPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_file(buf)
+    }
+}
+
+/// Writes to a shared reference to the port, which allows writing on one thread while another
+/// thread reads. See the thread safety section of the `COMPort` documentation.
+///
+/// RS-485 direction control changes the state of the port, so writing through a shared reference
+/// to a port with an RS-485 configuration fails with `io::ErrorKind::InvalidInput`.
+impl io::Write for &COMPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_vectored(&[io::IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        if self.options.read_only {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "port was opened read-only"));
+        }
+
+        if self.rs485.is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "RS-485 writes require a mutable reference to the port"));
+        }
+
+        self.write_file_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_file()
+    }
+}
+
+impl COMPort {
+    // Every read and write passes an OVERLAPPED, which works whether or not the handle was opened
+    // for overlapped I/O, e.g., for a handle passed to from_raw_handle().
+    fn read_file(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut overlapped = try!(Overlapped::new());
+
+        let started = unsafe {
+            ReadFile(self.handle, buf.as_mut_ptr() as *mut c_void, buf.len() as DWORD, ptr::null_mut(), overlapped.as_mut_ptr())
+        };
+
+        // the driver applies the read timeouts, and a read that was aborted returns no data
+        let len = try!(overlapped.finish(self.handle, started, None, super::error::last_io_error)).unwrap_or(0);
+
+        if len != 0 || buf.is_empty() {
+            Ok(len as usize)
+        }
+        else if self.timeouts.nonblocking {
+            Err(io::Error::new(io::ErrorKind::WouldBlock, "Operation would block"))
+        }
+        else {
+            if self.flush_on_timeout && unsafe { PurgeComm(self.handle, PURGE_RXCLEAR) } == 0 {
+                return Err(super::error::last_io_error());
+            }
+
+            Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"))
+        }
+    }
+
+    fn flush_file(&self) -> io::Result<()> {
+        match unsafe { FlushFileBuffers(self.handle) } {
+            0 => Err(super::error::last_io_error()),
+            _ => Ok(())
+        }
+    }
+
+    fn write_file(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut overlapped = try!(Overlapped::new());
+
+        let started = unsafe {
+            WriteFile(self.handle, buf.as_ptr() as *mut c_void, buf.len() as DWORD, ptr::null_mut(), overlapped.as_mut_ptr())
+        };
+
+        // the driver applies the write timeout, and a write that was aborted writes nothing
+        let len = try!(overlapped.finish(self.handle, started, None, super::error::last_io_error)).unwrap_or(0);
+
+        if len != 0 || buf.is_empty() {
+            Ok(len as usize)
+        }
+        else {
+            Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"))
+        }
+    }
+
+    fn write_file_vectored(&self, bufs: &[io::IoSlice]) -> io::Result<usize> {
+        let mut written = 0;
+
+        for buf in bufs.iter().filter(|buf| !buf.is_empty()) {
+            match self.write_file(buf) {
+                Ok(len) => {
+                    written += len;
+
+                    if len < buf.len() {
+                        break;
+                    }
+                },
+                // bytes that were already written must be reported, so the error is left for the
+                // next write
+                Err(_) if written > 0 => break,
+                Err(e) => return Err(e)
+            }
+        }
+
+        Ok(written)
+    }
+
+    fn write_rs485(&mut self, bufs: &[io::IoSlice], config: &Rs485Config) -> io::Result<usize> {
+        if let Some(e) = self.rs485_error.take() {
+            return Err(e);
+        }
+
+        try!(self.set_rts(config.rts_on_send));
+        thread::sleep(config.delay_before_send);
+
+        let written = self.write_file_vectored(bufs);
+
+        let drained = match written {
+            Ok(_) => self.drain(),
+            Err(_) => Ok(())
+        };
+
+        thread::sleep(config.delay_after_send);
+        let restored = self.set_rts(!config.rts_on_send);
+
+        let (result, held) = finish_rs485_write(written, drained.and(restored));
+        self.rs485_error = held;
+        result
+    }
+}
+
+// Combines the result of an RS-485 write with the result of draining it and returning RTS to the
+// receive level. Bytes that were written must be reported, so a failure after a successful write
+// is held for the next write.
+fn finish_rs485_write(written: io::Result<usize>, finished: ::Result<()>) -> (io::Result<usize>, Option<io::Error>) {
+    match (written, finished) {
+        (Ok(len), Err(e)) => (Ok(len), Some(e.into())),
+        (written, _) => (written, None)
+    }
+}
+
+impl SerialDevice for COMPort {
+    type Settings = COMSettings;
+
+    fn read_settings(&self) -> ::Result<COMSettings> {
+        let mut dcb = DCB::new();
+
+        match unsafe { GetCommState(self.handle, &mut dcb) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(COMSettings { inner: dcb })
+
+        }
+    }
+
+    fn write_settings(&mut self, settings: &COMSettings) -> ::Result<()> {
+        self.set_comm_state(settings)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeouts.read
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        let timeouts = Timeouts { read: timeout, ..self.timeouts };
+        self.apply_timeouts(&timeouts)
+    }
+
+    fn write_timeout(&self) -> Duration {
+        self.timeouts.write
+    }
+
+    fn set_write_timeout(&mut self, timeout: Duration) -> ::Result<()> {
+        let timeouts = Timeouts { write: timeout, ..self.timeouts };
+        self.apply_timeouts(&timeouts)
+    }
+
+    fn inter_byte_timeout(&self) -> Option<Duration> {
+        self.timeouts.inter_byte
+    }
+
+    fn set_inter_byte_timeout(&mut self, timeout: Option<Duration>) -> ::Result<()> {
+        let timeouts = Timeouts { inter_byte: timeout, ..self.timeouts };
+        self.apply_timeouts(&timeouts)
+    }
+
+    fn timeout_mode(&self) -> ::TimeoutMode {
+        self.timeouts.mode
+    }
+
+    fn set_timeout_mode(&mut self, mode: ::TimeoutMode) -> ::Result<()> {
+        let timeouts = Timeouts { mode: mode, ..self.timeouts };
+        self.apply_timeouts(&timeouts)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> ::Result<()> {
+        let timeouts = Timeouts { nonblocking: nonblocking, ..self.timeouts };
+        self.apply_timeouts(&timeouts)
+    }
+
+    fn is_exclusive(&self) -> bool {
+        !self.options.shared
+    }
+
+    fn set_exclusive(&mut self, _exclusive: bool) -> ::Result<()> {
+        // the share mode is passed to CreateFileW and can't be changed while the port is open
+        Ok(())
+    }
+
+    fn set_low_latency(&mut self, _low_latency: bool) -> ::Result<()> {
+        // the latency timer of USB adapters is a driver setting that can't be changed per handle
+        Ok(())
+    }
+
+    fn set_hangup_on_close(&mut self, _hangup: bool) -> ::Result<()> {
+        // the driver sets the state of DTR and RTS when the handle is closed
+        Ok(())
+    }
+
+    fn drain(&mut self) -> ::Result<()> {
+        let timeout = self.timeouts.write;
+        let start = Instant::now();
+
+        loop {
+            let comstat = try!(self.clear_comm_error());
+
+            if comstat.cbOutQue == 0 {
+                return Ok(());
+            }
+
+            if timeout != Duration::from_millis(0) && start.elapsed() >= timeout {
+                return Err(::Error::new(0, ::ErrorKind::Io(io::ErrorKind::TimedOut), "Operation timed out"));
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn clear(&mut self, buffer: ::ClearBuffer) -> ::Result<()> {
+        let flags = match buffer {
+            ::ClearBuffer::Input  => PURGE_RXCLEAR,
+            ::ClearBuffer::Output => PURGE_TXCLEAR,
+            ::ClearBuffer::All    => PURGE_RXCLEAR | PURGE_TXCLEAR
+        };
+
+        match unsafe { PurgeComm(self.handle, flags) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(())
+        }
+    }
+
+    fn wait_readable(&mut self, timeout: Duration) -> ::Result<bool> {
+        if !self.options.overlapped {
+            return self.poll_readable(timeout);
+        }
+
+        if timeout == Duration::from_millis(0) {
+            return Ok(try!(self.clear_comm_error()).cbInQue > 0);
+        }
+
+        let mut previous: DWORD = 0;
+
+        if unsafe { GetCommMask(self.handle, &mut previous) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        try!(self.set_event_mask(EventMask { rx_char: true, ..EventMask::default() }));
+
+        // EV_RXCHAR only reports bytes that arrive after the mask is set, so check the queue too
+        let result = match self.clear_comm_error() {
+            Ok(ref comstat) if comstat.cbInQue > 0 => Ok(true),
+            Ok(_) => match self.wait_comm_event(timeout) {
+                Ok(_) => Ok(true),
+                Err(ref e) if e.kind() == ::ErrorKind::Io(io::ErrorKind::TimedOut) => Ok(false),
+                Err(e) => Err(e)
+            },
+            Err(e) => Err(e)
+        };
+
+        unsafe { SetCommMask(self.handle, previous) };
+        result
+    }
+
+    fn bytes_available(&mut self) -> ::Result<usize> {
+        Ok(try!(self.clear_comm_error()).cbInQue as usize)
+    }
+
+    fn set_rts(&mut self, level: bool) -> ::Result<()> {
+        try!(self.escape_comm_function(if level { SETRTS } else { CLRRTS }));
+        self.rts = level;
+        Ok(())
+    }
+
+    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
+        try!(self.escape_comm_function(if level { SETDTR } else { CLRDTR }));
+        self.dtr = level;
+        Ok(())
+    }
+
+    fn set_break(&mut self) -> ::Result<()> {
+        self.escape_comm_function(SETBREAK)
+    }
+
+    fn clear_break(&mut self) -> ::Result<()> {
+        self.escape_comm_function(CLRBREAK)
+    }
+
+    fn modem_status(&mut self) -> ::Result<ModemStatus> {
+        let mut status: DWORD = 0;
+
+        match unsafe { GetCommModemStatus(self.handle, &mut status) } {
+            0 => Err(super::error::last_device_error()),
+            _ => Ok(ModemStatus {
+                cts: status & MS_CTS_ON != 0,
+                dsr: status & MS_DSR_ON != 0,
+                ri:  status & MS_RING_ON != 0,
+                cd:  status & MS_RLSD_ON != 0
+            })
+        }
+    }
+
+    fn wait_modem_change(&mut self, lines: ModemLines) -> ::Result<ModemStatus> {
+        if lines.is_empty() {
+            return Err(::Error::new(0, ::ErrorKind::InvalidInput, "no modem lines selected"));
+        }
+
+        let mut previous: DWORD = 0;
+
+        if unsafe { GetCommMask(self.handle, &mut previous) } == 0 {
+            return Err(super::error::last_device_error());
+        }
+
+        let mask = EventMask {
+            cts: lines.cts,
+            dsr: lines.dsr,
+            ring: lines.ri,
+            cd: lines.cd,
+            ..EventMask::default()
+        };
+
+        try!(self.set_event_mask(mask));
+        let result = self.wait_event();
+
+        unsafe { SetCommMask(self.handle, previous) };
+
+        try!(result);
+        self.modem_status()
+    }
+
+    fn read_cts(&mut self) -> ::Result<bool> {
+        self.modem_status().map(|status| status.cts)
+    }
+
+    fn read_dsr(&mut self) -> ::Result<bool> {
+        self.modem_status().map(|status| status.dsr)
+    }
+
+    fn read_ri(&mut self) -> ::Result<bool> {
+        self.modem_status().map(|status| status.ri)
+    }
+
+    fn read_cd(&mut self) -> ::Result<bool> {
+        self.modem_status().map(|status| status.cd)
+    }
+}
+
+/// RS-485 transceiver control settings.
+///
+/// RS-485 control is enabled with [`COMPort::set_rs485()`](struct.COMPort.html#method.set_rs485).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct Rs485Config {
+    /// The level of the RTS signal while transmitting.
+    ///
+    /// RTS is set to the opposite level while receiving.
+    pub rts_on_send: bool,
+
+    /// The time to wait after switching RTS to the transmit level before sending data.
+    pub delay_before_send: Duration,
+
+    /// The time to wait after the data has been sent before switching RTS to the receive level.
+    pub delay_after_send: Duration
+}
+
+impl Default for Rs485Config {
+    fn default() -> Self {
+        Rs485Config {
+            rts_on_send: true,
+            delay_before_send: Duration::from_millis(0),
+            delay_after_send: Duration::from_millis(0)
+        }
+    }
+}
+
+/// Hardware handshaking settings.
+///
+/// Each pair of signals can be enabled independently with
+/// [`COMSettings::set_handshake()`](struct.COMSettings.html#method.set_handshake). Enabling
+/// `rts_cts` is equivalent to `FlowHardware`.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub struct Handshake {
+    /// Output is suspended while CTS is deasserted, and RTS is asserted while there's room in the
+    /// input buffer.
+    pub rts_cts: bool,
+
+    /// Output is suspended while DSR is deasserted, and DTR is asserted while there's room in the
+    /// input buffer.
+    pub dtr_dsr: bool
+}
+
+/// How the driver controls the RTS signal.
+///
+/// The mode is selected with [`COMSettings::set_rts_mode()`](struct.COMSettings.html#method.set_rts_mode).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum RtsMode {
+    /// RTS is deasserted when the port is opened.
+    Disable,
+
+    /// RTS is asserted when the port is opened.
+    Enable,
+
+    /// RTS is asserted while there's room in the driver's input buffer.
+    Handshake,
+
+    /// RTS is asserted while there are bytes to transmit, which switches the direction of an
+    /// RS-485 transceiver without any help from the application.
+    Toggle
+}
+
+/// How the driver controls the DTR signal.
+///
+/// The mode is selected with [`COMSettings::set_dtr_mode()`](struct.COMSettings.html#method.set_dtr_mode).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub enum DtrMode {
+    /// DTR is deasserted when the port is opened.
+    Disable,
+
+    /// DTR is asserted when the port is opened.
+    Enable,
+
+    /// DTR is asserted while there's room in the driver's input buffer.
+    Handshake
+}
+
+/// Options for opening a COM port.
+///
+/// Options are passed to [`COMPort::open_with()`](struct.COMPort.html#method.open_with). By
+/// default, no options are set and the port is opened the same way as with `COMPort::open()`.
+#[derive(Debug,Copy,Clone,Default)]
+pub struct OpenOptions {
+    dtr: Option<bool>,
+    rts: Option<bool>,
+    settings: Option<COMSettings>,
+    read_only: bool,
+    shared: bool,
+    overlapped: bool
+}
+
+impl OpenOptions {
+    /// Creates a blank set of options.
+    pub fn new() -> Self {
+        OpenOptions::default()
+    }
+
+    /// Sets the state of the DTR control signal as soon as the port is opened.
+    ///
+    /// Many drivers assert DTR when a port is opened. Passing `false` deasserts it again before
+    /// the port is configured, which keeps boards that reset on DTR, such as Arduinos, running.
+    pub fn dtr(&mut self, level: bool) -> &mut Self {
+        self.dtr = Some(level);
+        self
+    }
+
+    /// Sets the state of the RTS control signal as soon as the port is opened.
+    pub fn rts(&mut self, level: bool) -> &mut Self {
+        self.rts = Some(level);
+        self
+    }
+
+    /// Writes settings to the port as soon as it's opened, before any other configuration.
+    ///
+    /// The settings include the control modes of DTR and RTS, so settings with
+    /// `DtrMode::Disable` keep DTR deasserted from the first moment that the crate controls the
+    /// port. Any level set with `dtr()` or `rts()` is applied after the settings.
+    ///
+    /// The order matters because the driver sets the control signals in `CreateFileW`, according
+    /// to the settings it has stored for the port, before `open_with()` can apply anything. Many
+    /// drivers keep the last settings written to a port until it's opened again, so once settings
+    /// with `DtrMode::Disable` have been written, later opens don't pulse DTR at all. Whether the
+    /// very first open after the device is plugged in pulses DTR depends on the driver's defaults
+    /// and can't be prevented from user code.
+    ///
+    /// ```no_run
+    /// use serial::windows::{COMPort,COMSettings,DtrMode,OpenOptions};
+    ///
+    /// let mut settings = COMSettings::standard(serial::Baud115200).unwrap();
+    /// settings.set_dtr_mode(DtrMode::Disable);
+    ///
+    /// COMPort::open_with("COM3", OpenOptions::new().settings(&settings)).unwrap();
+    /// ```
+    pub fn settings(&mut self, settings: &COMSettings) -> &mut Self {
+        self.settings = Some(*settings);
+        self
+    }
+
+    /// Opens the port without write access.
+    ///
+    /// See [`COMPort::open_readonly()`](struct.COMPort.html#method.open_readonly).
+    pub fn read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Opens the port for shared access.
+    ///
+    /// By default, a COM port is opened for exclusive access and opening it fails with `Busy`
+    /// while another handle to it is open. A shared port is opened with `FILE_SHARE_READ` and
+    /// `FILE_SHARE_WRITE`, so cooperating processes, such as a port monitor, can open it at the
+    /// same time. Most serial drivers refuse to share a port regardless, in which case opening it
+    /// still fails with `Busy`.
+    ///
+    /// Shared access is risky. The processes share the device's settings, timeouts, and control
+    /// signals, so a change made by one affects the others. Bytes written by different processes
+    /// are interleaved unpredictably, and each received byte is delivered to whichever process
+    /// happens to read it first.
+    pub fn shared(&mut self, shared: bool) -> &mut Self {
+        self.shared = shared;
+        self
+    }
+
+    /// Opens the port for overlapped I/O.
+    ///
+    /// Windows serializes I/O on a port that isn't opened for overlapped I/O, so a read that is
+    /// waiting for data holds up a write from another thread, and a `wait_event()` can't be
+    /// interrupted when its timeout expires. An overlapped port lets reads and writes through a
+    /// shared reference or a clone proceed at the same time, and waits for events until the
+    /// timeout expires rather than polling the driver.
+    ///
+    /// An overlapped port can also be converted into an `AsyncCOMPort`, which is available with
+    /// the optional `async` feature.
+    ///
+    /// The handle of an overlapped port can only be used with overlapped I/O, so it shouldn't be
+    /// converted into a `process::Stdio`, since most programs don't expect that for their standard
+    /// streams.
+    pub fn overlapped(&mut self, overlapped: bool) -> &mut Self {
+        self.overlapped = overlapped;
+        self
+    }
+}
+
+/// A set of COM port events.
+///
+/// Events are selected with [`COMPort::set_event_mask()`](struct.COMPort.html#method.set_event_mask)
+/// and reported by [`COMPort::wait_event()`](struct.COMPort.html#method.wait_event). Each field
+/// is `true` if the event is selected or has occurred.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub struct EventMask {
+    /// A character was received.
+    pub rx_char: bool,
+
+    /// The CTS (Clear To Send) signal changed state.
+    pub cts: bool,
+
+    /// The DSR (Data Set Ready) signal changed state.
+    pub dsr: bool,
+
+    /// A ring indicator was detected.
+    pub ring: bool,
+
+    /// The CD (Carrier Detect) signal changed state.
+    pub cd: bool,
+
+    /// A break condition was detected on input.
+    pub line_break: bool,
+
+    /// A line-status error occurred, i.e., a framing, overrun, or parity error.
+    pub error: bool
+}
+
+impl EventMask {
+    fn to_bits(self) -> DWORD {
+        let mut bits = 0;
+
+        if self.rx_char    { bits |= EV_RXCHAR; }
+        if self.cts        { bits |= EV_CTS; }
+        if self.dsr        { bits |= EV_DSR; }
+        if self.ring       { bits |= EV_RING; }
+        if self.cd         { bits |= EV_RLSD; }
+        if self.line_break { bits |= EV_BREAK; }
+        if self.error      { bits |= EV_ERR; }
+
+        bits
+    }
+
+    fn from_bits(bits: DWORD) -> Self {
+        EventMask {
+            rx_char:    bits & EV_RXCHAR != 0,
+            cts:        bits & EV_CTS != 0,
+            dsr:        bits & EV_DSR != 0,
+            ring:       bits & EV_RING != 0,
+            cd:         bits & EV_RLSD != 0,
+            line_break: bits & EV_BREAK != 0,
+            error:      bits & EV_ERR != 0
+        }
+    }
+}
+
+/// Line errors detected by a COM port.
+///
+/// Line errors are reported by [`COMPort::line_errors()`](struct.COMPort.html#method.line_errors).
+/// Each field is `true` if the error has occurred.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub struct LineErrors {
+    /// The driver's input buffer overflowed, so received characters were lost.
+    pub buffer_overflow: bool,
+
+    /// A character was received before the previous one was read out of the hardware.
+    pub overrun: bool,
+
+    /// A parity error was detected.
+    pub parity: bool,
+
+    /// A framing error was detected.
+    pub framing: bool,
+
+    /// A break condition was detected.
+    pub line_break: bool
+}
+
+impl LineErrors {
+    /// Returns `true` if any line error has occurred.
+    pub fn any(&self) -> bool {
+        self.buffer_overflow || self.overrun || self.parity || self.framing || self.line_break
+    }
+
+    fn from_bits(bits: DWORD) -> Self {
+        LineErrors {
+            buffer_overflow: bits & CE_RXOVER != 0,
+            overrun:         bits & CE_OVERRUN != 0,
+            parity:          bits & CE_RXPARITY != 0,
+            framing:         bits & CE_FRAME != 0,
+            line_break:      bits & CE_BREAK != 0
+        }
+    }
+}
+
+/// A snapshot of a COM port's complete configuration.
+///
+/// A snapshot is taken with [`COMPort::save_state()`](struct.COMPort.html#method.save_state) and
+/// restored with [`COMPort::restore_state()`](struct.COMPort.html#method.restore_state). Its
+/// contents are opaque.
+#[derive(Debug,Copy,Clone)]
+pub struct PortState {
+    settings: COMSettings,
+    comm_timeouts: COMMTIMEOUTS,
+    timeouts: Timeouts,
+    rts: bool,
+    dtr: bool
+}
+
+/// The state of a COM port's driver queues.
+///
+/// Queue status is returned by [`COMPort::queue_status()`](struct.COMPort.html#method.queue_status).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct QueueStatus {
+    /// The number of bytes received but not yet read.
+    pub bytes_in: u64,
+
+    /// The number of bytes written but not yet transmitted.
+    pub bytes_out: u64,
+
+    /// The line errors that occurred since they were last cleared.
+    pub errors: LineErrors
+}
+
+/// Capabilities and queue sizes reported by a COM port driver.
+///
+/// Properties are returned by [`COMPort::comm_properties()`](struct.COMPort.html#method.comm_properties).
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct CommProperties {
+    /// The maximum baud rate supported by the driver.
+    ///
+    /// This is `None` if the driver accepts arbitrary baud rates.
+    pub max_baud: Option<::BaudRate>,
+
+    /// The maximum size of the driver's transmit queue in bytes, or `None` if there is no limit.
+    pub max_tx_queue: Option<u32>,
+
+    /// The maximum size of the driver's receive queue in bytes, or `None` if there is no limit.
+    pub max_rx_queue: Option<u32>,
+
+    /// The current size of the driver's transmit queue in bytes.
+    pub current_tx_queue: u32,
+
+    /// The current size of the driver's receive queue in bytes.
+    pub current_rx_queue: u32,
+
+    /// The baud rates that can be selected.
+    pub settable_baud: SettableBaud,
+
+    /// The communication parameters that can be changed.
+    pub settable_params: SettableParams
+}
+
+impl CommProperties {
+    fn from_raw(props: &LPCOMMPROP) -> Self {
+        CommProperties {
+            max_baud:         baud_from_bit(props.dwMaxBaud),
+            max_tx_queue:     non_zero(props.dwMaxTxQueue),
+            max_rx_queue:     non_zero(props.dwMaxRxQueue),
+            current_tx_queue: props.dwCurrentTxQueue,
+            current_rx_queue: props.dwCurrentRxQueue,
+            settable_baud:    SettableBaud { bits: props.dwSettableBaud },
+            settable_params:  SettableParams::from_bits(props.dwSettableParams)
+        }
+    }
+}
+
+/// The set of baud rates that a COM port driver allows to be selected.
+#[derive(Debug,Copy,Clone,PartialEq,Eq)]
+pub struct SettableBaud {
+    bits: DWORD
+}
+
+impl SettableBaud {
+    /// Returns `true` if the given baud rate can be selected.
+    ///
+    /// Every baud rate can be selected if the driver accepts arbitrary baud rates.
+    pub fn contains(&self, baud_rate: ::BaudRate) -> bool {
+        if self.is_programmable() {
+            return true;
+        }
+
+        match BAUD_BITS.iter().find(|&&(_, speed)| speed == baud_rate.speed()) {
+            Some(&(bit, _)) => self.bits & bit != 0,
+            None => false
+        }
+    }
+
+    /// Returns `true` if the driver accepts arbitrary baud rates.
+    pub fn is_programmable(&self) -> bool {
+        self.bits & BAUD_USER != 0
+    }
+}
+
+/// Communication parameters that can be changed on a COM port.
+///
+/// Each field is `true` if the parameter can be changed.
+#[derive(Debug,Copy,Clone,PartialEq,Eq,Default)]
+pub struct SettableParams {
+    /// The baud rate.
+    pub baud_rate: bool,
+
+    /// The character size.
+    pub char_size: bool,
+
+    /// The parity mode.
+    pub parity: bool,
+
+    /// Whether parity checking is enabled.
+    pub parity_check: bool,
+
+    /// The number of stop bits.
+    pub stop_bits: bool,
+
+    /// The flow control mode.
+    pub flow_control: bool,
+
+    /// Whether the CD signal is monitored.
+    pub carrier_detect: bool
+}
+
+impl SettableParams {
+    fn from_bits(bits: DWORD) -> Self {
+        SettableParams {
+            baud_rate:      bits & SP_BAUD != 0,
+            char_size:      bits & SP_DATABITS != 0,
+            parity:         bits & SP_PARITY != 0,
+            parity_check:   bits & SP_PARITY_CHECK != 0,
+            stop_bits:      bits & SP_STOPBITS != 0,
+            flow_control:   bits & SP_HANDSHAKING != 0,
+            carrier_detect: bits & SP_RLSD != 0
+        }
+    }
+}
+
+/// The baud rates and parameters supported by a COM port.
+///
+/// Capabilities are returned by [`COMPort::capabilities()`](struct.COMPort.html#method.capabilities).
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Capabilities {
+    /// The maximum baud rate in bits per second, or zero if the driver accepts arbitrary baud
+    /// rates without reporting a maximum.
+    pub max_baud: u32,
+
+    /// The standard baud rates that can be selected, from slowest to fastest.
+    pub supported_baud_rates: Vec<::BaudRate>,
+
+    /// `true` if baud rates other than those in `supported_baud_rates` can be selected.
+    pub programmable_baud: bool,
+
+    /// The communication parameters that can be changed.
+    pub settable_params: SettableParams,
+
+    /// The size of the properties structure reported by the driver, in bytes.
+    pub packet_length: u16
+}
+
+impl Capabilities {
+    fn from_raw(props: &LPCOMMPROP) -> Self {
+        Capabilities {
+            max_baud: baud_from_bit(props.dwMaxBaud).map_or(0, |baud_rate| baud_rate.speed() as u32),
+            supported_baud_rates: BAUD_BITS.iter()
+                .filter(|&&(bit, _)| props.dwSettableBaud & bit != 0)
+                .map(|&(_, speed)| ::BaudRate::from_speed(speed))
+                .collect(),
+            programmable_baud: props.dwSettableBaud & BAUD_USER != 0,
+            settable_params: SettableParams::from_bits(props.dwSettableParams),
+            packet_length: props.wPacketLength
+        }
+    }
+}
+
+// the BAUD_* flags and the speeds they stand for; BAUD_134_5 can't be represented by BaudRate
+const BAUD_BITS: [(DWORD, usize); 18] = [
+    (BAUD_075,    75),
+    (BAUD_110,    110),
+    (BAUD_150,    150),
+    (BAUD_300,    300),
+    (BAUD_600,    600),
+    (BAUD_1200,   1200),
+    (BAUD_1800,   1800),
+    (BAUD_2400,   2400),
+    (BAUD_4800,   4800),
+    (BAUD_7200,   7200),
+    (BAUD_9600,   9600),
+    (BAUD_14400,  14400),
+    (BAUD_19200,  19200),
+    (BAUD_38400,  38400),
+    (BAUD_56K,    56000),
+    (BAUD_57600,  57600),
+    (BAUD_115200, 115200),
+    (BAUD_128K,   128000)
+];
+
+fn baud_from_bit(bit: DWORD) -> Option<::BaudRate> {
+    BAUD_BITS.iter()
+        .find(|&&(flag, _)| flag == bit)
+        .map(|&(_, speed)| ::BaudRate::from_speed(speed))
+}
+
+fn baud_error(requested: DWORD, actual: DWORD) -> Option<f64> {
+    match requested {
+        0 => None,
+        _ => Some((actual as f64 - requested as f64) / requested as f64 * 100.0)
+    }
+}
+
+fn non_zero(value: DWORD) -> Option<u32> {
+    match value {
+        0 => None,
+        n => Some(n)
+    }
+}
+
+fn create_file(port: &OsStr, options: &OpenOptions) -> ::Result<HANDLE> {
+    let name = device_path(port);
+
+    let access = if options.read_only {
+        GENERIC_READ
+    }
+    else {
+        GENERIC_READ | GENERIC_WRITE
+    };
+
+    let share_mode = if options.shared {
+        FILE_SHARE_READ | FILE_SHARE_WRITE
+    }
+    else {
+        0
+    };
+
+    let flags = if options.overlapped {
+        FILE_ATTRIBUTE_NORMAL | FILE_FLAG_OVERLAPPED
+    }
+    else {
+        FILE_ATTRIBUTE_NORMAL
+    };
+
+    let handle = unsafe {
+        CreateFileW(name.as_ptr(), access, share_mode, ptr::null_mut(), OPEN_EXISTING, flags, 0 as HANDLE)
+    };
+
+    if handle != INVALID_HANDLE_VALUE {
+        Ok(handle)
+    }
+    else {
+        Err(super::error::last_os_error())
+    }
+}
+
+/// The timeouts that are applied to the device with `SetCommTimeouts`.
+#[derive(Copy,Clone,Debug)]
+struct Timeouts {
+    read: Duration,
+    write: Duration,
+    inter_byte: Option<Duration>,
+    mode: ::TimeoutMode,
+    nonblocking: bool
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            read: Duration::from_millis(100),
+            write: Duration::from_millis(0),
+            inter_byte: None,
+            mode: ::TimeoutMode::Total,
+            nonblocking: false
+        }
+    }
+}
+
+impl Timeouts {
+    fn to_comm_timeouts(self) -> COMMTIMEOUTS {
+        // a read interval of zero disables the interval timeout, so round short intervals up
+        let (interval, constant) = match self.mode {
+            ::TimeoutMode::Total => {
+                let interval = match self.inter_byte {
+                    Some(inter_byte) => cmp::max(duration_to_millis(inter_byte), 1),
+                    None => 0
+                };
+
+                (interval, duration_to_millis(self.read))
+            },
+            // with only an interval timeout, ReadFile waits for the first byte indefinitely
+            ::TimeoutMode::InterByte => (cmp::max(duration_to_millis(self.read), 1), 0),
+            ::TimeoutMode::None => (0, 0)
+        };
+
+        let mut timeouts = COMMTIMEOUTS {
+            ReadIntervalTimeout: interval,
+            ReadTotalTimeoutMultiplier: 0,
+            ReadTotalTimeoutConstant: constant,
+            WriteTotalTimeoutMultiplier: 0,
+            WriteTotalTimeoutConstant: duration_to_millis(self.write)
+        };
+
+        // A read interval of MAXDWORD with zero total timeouts makes ReadFile return immediately
+        // with whatever bytes have already been received. Without it, a zero timeout would make
+        // ReadFile wait indefinitely.
+        let zero = self.read == Duration::from_millis(0) && self.mode != ::TimeoutMode::None;
+
+        if self.nonblocking || zero {
+            timeouts.ReadIntervalTimeout = MAXDWORD;
+            timeouts.ReadTotalTimeoutConstant = 0;
+        }
+
+        timeouts
+    }
+}
+
+/// Returns the null-terminated device path of a COM port.
+///
+/// Names of COM ports above COM9 can only be opened through the `\\.\` device namespace. The
+/// prefix is added unless the name already starts with it.
+fn device_path(port: &OsStr) -> Vec<u16> {
+    let prefix: Vec<u16> = OsStr::new("\\\\.\\").encode_wide().collect();
+    let port: Vec<u16> = port.encode_wide().collect();
+
+    let mut name = prefix.clone();
+
+    if port.starts_with(&prefix) {
+        name.extend_from_slice(&port[prefix.len()..]);
+    }
+    else {
+        name.extend_from_slice(&port);
+    }
+
+    name.push(0);
+    name
+}
+
+pub(crate) fn duration_to_millis(duration: Duration) -> DWORD {
+    // COMMTIMEOUTS treats zero as no timeout at all, so partial milliseconds are rounded up, and
+    // long durations saturate rather than wrapping around to a short or infinite timeout
+    let nanos = duration.subsec_nanos();
+    let subsec_millis = u64::from(nanos / 1_000_000) + u64::from(nanos % 1_000_000 != 0);
+
+    let milliseconds = duration.as_secs()
+        .checked_mul(1000)
+        .and_then(|millis| millis.checked_add(subsec_millis))
+        .unwrap_or(u64::MAX);
+
+    cmp::min(milliseconds, (MAXDWORD - 1) as u64) as DWORD
+}
+
+/// The driver's status as polled by `wait_event()` on a port that isn't opened for overlapped I/O.
+#[derive(Copy,Clone,Debug,Default)]
+struct PolledStatus {
+    bytes_in: DWORD,
+    modem: DWORD,
+    errors: DWORD
+}
+
+impl PolledStatus {
+    // the events that WaitCommEvent would have reported since the previous poll
+    fn events_since(&self, previous: &PolledStatus) -> DWORD {
+        let changed = self.modem ^ previous.modem;
+        let mut events = 0;
+
+        if self.bytes_in > previous.bytes_in { events |= EV_RXCHAR; }
+        if changed & MS_CTS_ON != 0          { events |= EV_CTS; }
+        if changed & MS_DSR_ON != 0          { events |= EV_DSR; }
+        if changed & MS_RING_ON != 0         { events |= EV_RING; }
+        if changed & MS_RLSD_ON != 0         { events |= EV_RLSD; }
+        if self.errors & CE_BREAK != 0       { events |= EV_BREAK; }
+
+        if self.errors & (CE_FRAME | CE_OVERRUN | CE_RXPARITY) != 0 {
+            events |= EV_ERR;
+        }
+
+        events
+    }
+}
+
+
+/// Serial port settings for COM ports.
+///
+/// Two `COMSettings` compare equal if they have the same baud rate, character size, parity, stop
+/// bits, flow control, and hardware handshaking. This is a comparison of the configuration they
+/// represent, not of their raw contents, so settings read back from a device compare equal to the
+/// settings that were written even if the driver filled in reserved fields.
+///
+/// COM ports have no line discipline like the termios settings of a POSIX tty. The driver always
+/// transfers data in binary mode, so CR, LF, and control characters such as `^C` pass through
+/// unchanged. Received data is only altered by null stripping and the error replacement
+/// character, which are disabled in the settings created by `builder()`; see
+/// `set_null_stripping()` and `set_error_char()`.
+#[derive(Copy,Clone,Debug)]
+pub struct COMSettings {
+    inner: DCB
+}
+
+impl PartialEq for COMSettings {
+    fn eq(&self, other: &COMSettings) -> bool {
+        self.baud_rate() == other.baud_rate()
+            && self.char_size() == other.char_size()
+            && self.parity() == other.parity()
+            && self.stop_bits() == other.stop_bits()
+            && self.flow_control() == other.flow_control()
+            && self.handshake() == other.handshake()
+    }
+}
+
+impl Eq for COMSettings {}
+
+impl COMSettings {
+    /// Returns a builder for creating a complete set of COM port settings.
+    ///
+    /// The builder starts from 9600 baud, 8 data bits, no parity, one stop bit, and no flow
+    /// control, so only the settings that differ from these defaults need to be provided.
+    ///
+    /// ```no_run
+    /// use serial::windows::{COMPort,COMSettings};
+    ///
+    /// let settings = COMSettings::builder()
+    ///     .baud_rate(serial::Baud115200)
+    ///     .parity(serial::ParityEven)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let mut port = COMPort::open("COM1").unwrap();
+    /// port.write_settings(&settings).unwrap();
+    /// ```
+    pub fn builder() -> COMSettingsBuilder {
+        COMSettingsBuilder {
+            settings: PortSettings::default()
+        }
+    }
+
+    /// Creates settings for 8 data bits, no parity, and one stop bit (8N1) at the given baud rate.
+    ///
+    /// This is the most common configuration. Flow control is disabled.
+    ///
+    /// ```no_run
+    /// use serial::windows::{COMPort,COMSettings};
+    ///
+    /// let mut port = COMPort::open("COM1").unwrap();
+    /// port.write_settings(&COMSettings::standard(serial::Baud115200).unwrap()).unwrap();
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the baud rate is not supported for COM ports.
+    pub fn standard(baud_rate: ::BaudRate) -> ::Result<COMSettings> {
+        COMSettings::builder().baud_rate(baud_rate).build()
+    }
+
+    /// Creates settings for 7 data bits, even parity, and one stop bit (7E1) at the given baud
+    /// rate.
+    ///
+    /// This configuration is used by some industrial protocols, such as Modbus ASCII. Flow control
+    /// is disabled.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the baud rate is not supported for COM ports.
+    pub fn seven_e_1(baud_rate: ::BaudRate) -> ::Result<COMSettings> {
+        COMSettings::builder()
+            .baud_rate(baud_rate)
+            .char_size(::Bits7)
+            .parity(::ParityEven)
+            .build()
+    }
+
+    /// Returns `true` if the settings use 8 data bits, no parity, and one stop bit.
+    ///
+    /// The baud rate and flow control mode are not considered.
+    pub fn is_8n1(&self) -> bool {
+        self.char_size() == Some(::Bits8) && self.parity() == Some(::ParityNone) && self.stop_bits() == Some(::Stop1)
+    }
+
+    /// Returns the XON and XOFF characters used for software flow control.
+    pub fn flow_control_chars(&self) -> (u8, u8) {
+        (self.inner.XonChar as u8, self.inner.XoffChar as u8)
+    }
+
+    /// Sets the XON and XOFF characters used for software flow control.
+    ///
+    /// The characters only take effect when software flow control is enabled with
+    /// `set_flow_control(FlowSoftware)`. The driver defaults are usually `0x11` (DC1) for XON and
+    /// `0x13` (DC3) for XOFF.
+    pub fn set_flow_control_chars(&mut self, xon: u8, xoff: u8) {
+        self.inner.XonChar = xon as c_char;
+        self.inner.XoffChar = xoff as c_char;
+    }
+
+    /// Returns the XON and XOFF thresholds used for software flow control.
+    pub fn flow_control_limits(&self) -> (u16, u16) {
+        (self.inner.XonLim, self.inner.XoffLim)
+    }
+
+    /// Sets the XON and XOFF thresholds used for software flow control.
+    ///
+    /// XOFF is sent when the free space in the driver's input buffer drops to `xoff_lim` bytes,
+    /// and XON is sent once the number of bytes in the input buffer drops to `xon_lim`.
+    pub fn set_flow_control_limits(&mut self, xon_lim: u16, xoff_lim: u16) {
+        self.inner.XonLim = xon_lim;
+        self.inner.XoffLim = xoff_lim;
+    }
+
+    /// Returns the character size, or an error if it isn't one that `CharSize` can represent.
+    ///
+    /// Settings written through this crate always have a valid character size, but another
+    /// program may have left the port configured with one that isn't, e.g., 4 bits. Unlike
+    /// `char_size()`, which returns `None` in that case, this reports the actual size.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if the character size is not between 5 and 8 bits.
+    pub fn try_char_size(&self) -> ::Result<::CharSize> {
+        ::CharSize::from_bits(self.inner.ByteSize)
+    }
+
+    /// Returns the time it takes to transmit one character with these settings.
+    ///
+    /// A character is framed by a start bit, the data bits, a parity bit if parity is enabled,
+    /// and the stop bits. This is useful for deriving timeouts from the settings, e.g., an
+    /// inter-byte timeout of a few character times. Returns `None` if the baud rate is zero or
+    /// the framing can't be determined.
+    pub fn frame_duration(&self) -> Option<Duration> {
+        let baud = match self.baud_rate() {
+            Some(baud_rate) if baud_rate.speed() > 0 => baud_rate.speed() as u64,
+            _ => return None
+        };
+
+        let data_bits = match self.char_size() {
+            Some(char_size) => char_size.bits() as u64,
+            None => return None
+        };
+
+        let parity_bits = match self.parity() {
+            Some(::ParityNone) => 0,
+            Some(_) => 1,
+            None => return None
+        };
+
+        // counted in half bits to allow for 1.5 stop bits
+        let stop_half_bits = match self.stop_bits() {
+            Some(::Stop1) => 2,
+            Some(::Stop1_5) => 3,
+            Some(::Stop2) => 4,
+            None => return None
+        };
+
+        let half_bits = 2 * (1 + data_bits + parity_bits) + stop_half_bits;
+        let nanos = half_bits * 1_000_000_000 / (2 * baud);
+
+        Some(Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32))
+    }
+
+    /// Returns `true` if received characters are checked for parity errors.
+    pub fn parity_check(&self) -> bool {
+        self.inner.fBits & fParity != 0
+    }
+
+    /// Enables or disables parity checking of received characters.
+    ///
+    /// Parity checking is disabled by default, in which case the parity bit is generated for
+    /// transmitted characters but ignored for received ones. Parity checking has no effect while
+    /// the parity mode is `ParityNone`.
+    pub fn set_parity_check(&mut self, check: bool) {
+        if check {
+            self.inner.fBits |= fParity;
         }
-    }
-    pub fn purge_txabort(&mut self) -> ::Result<()> {
-        match unsafe{ PurgeComm(self.handle, PURGE_TXABORT ) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(())
+        else {
+            self.inner.fBits &= !fParity;
         }
     }
-    pub fn purge_txclear(&mut self) -> ::Result<()> {
-        match unsafe{ PurgeComm(self.handle, PURGE_TXCLEAR ) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(())
+
+    /// Returns the character that replaces received characters with parity errors, if any.
+    pub fn error_char(&self) -> Option<u8> {
+        match self.inner.fBits & fErrorChar {
+            0 => None,
+            _ => Some(self.inner.ErrorChar as u8)
         }
     }
 
-    /// status operations
-    pub fn bytes_in(&mut self) -> ::Result<u64> {
-        let b = Box::new(COMSTAT::default());
-        let p: *mut COMSTAT = unsafe{ mem::transmute(b)};
-        let mut status: DWORD = unsafe { mem::uninitialized() };
-        match unsafe{ ClearCommError( self.handle, &mut status, p ) } {
-            0 => Err(super::error::last_os_error()),
-            _ => {
-                let p: &COMSTAT = unsafe{ mem::transmute(p)};
-                Ok(p.cbInQue as u64)
+    /// Sets the character that replaces received characters with parity errors.
+    ///
+    /// Passing `None` delivers characters with parity errors unchanged. The replacement only
+    /// happens while parity checking is enabled with `set_parity_check(true)` and a parity mode
+    /// other than `ParityNone` is set.
+    pub fn set_error_char(&mut self, error_char: Option<u8>) {
+        match error_char {
+            Some(c) => {
+                self.inner.fBits |= fErrorChar;
+                self.inner.ErrorChar = c as c_char;
+            },
+            None => {
+                self.inner.fBits &= !fErrorChar;
+                self.inner.ErrorChar = 0;
             }
         }
     }
-    /// status operations
-    pub fn bytes_out(&mut self) -> ::Result<u64> {
-        let b = Box::new(COMSTAT::default());
-        let p: *mut COMSTAT = unsafe{ mem::transmute(b)};
-        let mut status: DWORD = unsafe { mem::uninitialized() };
-        match unsafe{ ClearCommError( self.handle, &mut status, p ) } {
-            0 => Err(super::error::last_os_error()),
-            _ => {
-                let p: &COMSTAT = unsafe{ mem::transmute(p)};
-                Ok(p.cbOutQue as u64)
-            }
-        }
+
+    /// Returns `true` if received null bytes are discarded.
+    pub fn null_stripping(&self) -> bool {
+        self.inner.fBits & fNull != 0
     }
 
-    /// write settings
-    pub fn write_settings(&mut self, settings: &COMSettings) -> ::Result<()> {
-        match unsafe { SetCommState(self.handle, &settings.inner) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(())
+    /// Enables or disables discarding received null bytes.
+    ///
+    /// Unlike the error character, null stripping doesn't depend on parity checking.
+    pub fn set_null_stripping(&mut self, strip: bool) {
+        if strip {
+            self.inner.fBits |= fNull;
+        }
+        else {
+            self.inner.fBits &= !fNull;
         }
     }
-}
 
-impl Drop for COMPort {
-    fn drop(&mut self) {
-        unsafe {
-            CloseHandle(self.handle);
-        }
+    /// Returns `true` if received bytes are discarded while DSR is deasserted.
+    pub fn dsr_sensitivity(&self) -> bool {
+        self.inner.fBits & fDsrSensitivity != 0
     }
-}
 
-impl AsRawHandle for COMPort {
-    fn as_raw_handle(&self) -> RawHandle {
-        unsafe {
-            mem::transmute(self.handle)
+    /// Enables or disables DSR sensitivity (`fDsrSensitivity`).
+    ///
+    /// While DSR sensitivity is enabled, the driver ignores every byte that arrives while the
+    /// DSR signal is deasserted, so only data sent while the other end reports that it's ready is
+    /// received. This doesn't affect output; output flow control on DSR is enabled with
+    /// `set_handshake()`.
+    pub fn set_dsr_sensitivity(&mut self, sensitive: bool) {
+        if sensitive {
+            self.inner.fBits |= fDsrSensitivity;
+        }
+        else {
+            self.inner.fBits &= !fDsrSensitivity;
         }
     }
-}
 
-impl io::Read for COMPort {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut len: DWORD = 0;
+    /// Returns `true` if transmission continues after the driver has sent XOFF.
+    pub fn tx_continue_on_xoff(&self) -> bool {
+        self.inner.fBits & fTXContinueOnXoff != 0
+    }
 
-        match unsafe { ReadFile(self.handle, buf.as_mut_ptr() as *mut c_void, buf.len() as DWORD, &mut len, ptr::null_mut()) } {
-            0 => Err(io::Error::last_os_error()),
-            _ => {
-                if len != 0 {
-                    Ok(len as usize)
-                }
-                else {
-                    Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"))
-                }
-            }
+    /// Controls whether transmission continues after the driver has sent XOFF
+    /// (`fTXContinueOnXoff`).
+    ///
+    /// With input flow control, the driver sends XOFF when its input buffer is nearly full. By
+    /// default, it then also stops transmitting until it has sent XON again, which suits devices
+    /// that treat XOFF and XON as an exchange. When this flag is enabled, output continues while
+    /// input is held off, which full-duplex devices that only pause their own output need. The
+    /// flag has no effect unless software flow control is enabled.
+    pub fn set_tx_continue_on_xoff(&mut self, continue_on_xoff: bool) {
+        if continue_on_xoff {
+            self.inner.fBits |= fTXContinueOnXoff;
+        }
+        else {
+            self.inner.fBits &= !fTXContinueOnXoff;
         }
     }
-}
 
-impl io::Write for COMPort {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut len: DWORD = 0;
+    /// Returns `true` if a line error halts reads and writes until it's cleared.
+    pub fn abort_on_error(&self) -> bool {
+        self.inner.fBits & fAbortOnError != 0
+    }
 
-        match unsafe { WriteFile(self.handle, buf.as_ptr() as *mut c_void, buf.len() as DWORD, &mut len, ptr::null_mut()) } {
-            0 => Err(io::Error::last_os_error()),
-            _ => Ok(len as usize)
+    /// Controls whether a line error halts reads and writes until it's cleared (`fAbortOnError`).
+    ///
+    /// When enabled, a framing, parity, or overrun error makes the driver abort pending reads and
+    /// writes, and every following read and write fails until the error is acknowledged with
+    /// [`COMPort::clear_errors()`](struct.COMPort.html#method.clear_errors). This guarantees that
+    /// an error is noticed before any more data is processed, but a port whose errors are never
+    /// cleared appears to stop receiving. When disabled, I/O continues after an error, and the
+    /// errors can still be inspected with `line_errors()`, but bytes received around the error may
+    /// be corrupted without the read reporting it.
+    ///
+    /// The default depends on the driver; some drivers enable it. The flag is left as the driver
+    /// reports it when a port is opened, so it's disabled by writing settings, e.g., with
+    /// [`OpenOptions::settings()`](struct.OpenOptions.html#method.settings).
+    pub fn set_abort_on_error(&mut self, abort: bool) {
+        if abort {
+            self.inner.fBits |= fAbortOnError;
+        }
+        else {
+            self.inner.fBits &= !fAbortOnError;
         }
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        match unsafe { FlushFileBuffers(self.handle) } {
-            0 => Err(io::Error::last_os_error()),
-            _ => Ok(())
+    /// Returns the hardware handshaking settings.
+    ///
+    /// A pair of signals is reported as enabled only if both the output flow control flag and the
+    /// handshaking mode of the control signal are set.
+    pub fn handshake(&self) -> Handshake {
+        Handshake {
+            rts_cts: self.inner.fBits & fOutxCtsFlow != 0 && self.inner.fBits & fRtsControl == RTS_CONTROL_HANDSHAKE,
+            dtr_dsr: self.inner.fBits & fOutxDsrFlow != 0 && self.inner.fBits & fDtrControl == DTR_CONTROL_HANDSHAKE
         }
     }
-}
 
-impl SerialDevice for COMPort {
-    type Settings = COMSettings;
+    /// Sets the hardware handshaking settings.
+    ///
+    /// Disabling handshaking on a pair of signals leaves its control signal asserted.
+    pub fn set_handshake(&mut self, handshake: Handshake) {
+        if handshake.rts_cts {
+            self.inner.fBits = (self.inner.fBits & !fRtsControl) | fOutxCtsFlow | RTS_CONTROL_HANDSHAKE;
+        }
+        else {
+            self.inner.fBits &= !fOutxCtsFlow;
 
-    fn read_settings(&self) -> ::Result<COMSettings> {
-        let mut dcb = DCB::new();
+            if self.inner.fBits & fRtsControl == RTS_CONTROL_HANDSHAKE {
+                self.inner.fBits = (self.inner.fBits & !fRtsControl) | RTS_CONTROL_ENABLE;
+            }
+        }
 
-        match unsafe { GetCommState(self.handle, &mut dcb) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(COMSettings { inner: dcb })
+        if handshake.dtr_dsr {
+            self.inner.fBits = (self.inner.fBits & !fDtrControl) | fOutxDsrFlow | DTR_CONTROL_HANDSHAKE;
+        }
+        else {
+            self.inner.fBits &= !fOutxDsrFlow;
 
+            if self.inner.fBits & fDtrControl == DTR_CONTROL_HANDSHAKE {
+                self.inner.fBits = (self.inner.fBits & !fDtrControl) | DTR_CONTROL_ENABLE;
+            }
         }
     }
 
-    fn write_settings(&mut self, settings: &COMSettings) -> ::Result<()> {
-        match unsafe { SetCommState(self.handle, &settings.inner) } {
-            0 => Err(super::error::last_os_error()),
-            _ => Ok(())
+    /// Returns how the driver controls the RTS signal.
+    pub fn rts_mode(&self) -> RtsMode {
+        match self.inner.fBits & fRtsControl {
+            RTS_CONTROL_DISABLE => RtsMode::Disable,
+            RTS_CONTROL_ENABLE => RtsMode::Enable,
+            RTS_CONTROL_HANDSHAKE => RtsMode::Handshake,
+            _ => RtsMode::Toggle
         }
     }
 
-    fn timeout(&self) -> Duration {
-        self.timeout
+    /// Sets how the driver controls the RTS signal.
+    ///
+    /// Only the RTS control mode is changed. Output flow control on CTS is enabled separately
+    /// with `set_handshake()` or `set_flow_control()`, which also change the RTS control mode.
+    ///
+    /// The `Handshake` and `Toggle` modes leave RTS under the control of the driver, so calling
+    /// `set_rts()` while either one is active conflicts with the driver and may fail or have its
+    /// effect undone. In the `Disable` and `Enable` modes, the mode only sets the initial level and
+    /// `set_rts()` controls the signal directly.
+    pub fn set_rts_mode(&mut self, mode: RtsMode) {
+        let bits = match mode {
+            RtsMode::Disable => RTS_CONTROL_DISABLE,
+            RtsMode::Enable => RTS_CONTROL_ENABLE,
+            RtsMode::Handshake => RTS_CONTROL_HANDSHAKE,
+            RtsMode::Toggle => RTS_CONTROL_TOGGLE
+        };
+
+        self.inner.fBits = (self.inner.fBits & !fRtsControl) | bits;
     }
 
-    fn set_timeout(&mut self, timeout: Duration) -> ::Result<()> {
-        let milliseconds = timeout.as_secs() * 1000 + timeout.subsec_nanos() as u64 / 1_000_000;
+    /// Returns how the driver controls the DTR signal.
+    pub fn dtr_mode(&self) -> DtrMode {
+        match self.inner.fBits & fDtrControl {
+            DTR_CONTROL_DISABLE => DtrMode::Disable,
+            DTR_CONTROL_HANDSHAKE => DtrMode::Handshake,
+            // the fourth value of the two-bit field is undefined and treated as enabled
+            _ => DtrMode::Enable
+        }
+    }
 
-        let timeouts = COMMTIMEOUTS {
-            ReadIntervalTimeout: 0,
-            ReadTotalTimeoutMultiplier: 0,
-            ReadTotalTimeoutConstant: milliseconds as DWORD,
-            WriteTotalTimeoutMultiplier: 0,
-            WriteTotalTimeoutConstant: 0
+    /// Sets how the driver controls the DTR signal.
+    ///
+    /// Only the DTR control mode is changed. Output flow control on DSR is enabled separately with
+    /// `set_handshake()`, which also changes the DTR control mode.
+    ///
+    /// The `Handshake` mode leaves DTR under the control of the driver, so calling `set_dtr()`
+    /// while it's active conflicts with the driver and may fail or have its effect undone. In the
+    /// `Disable` and `Enable` modes, the mode only sets the initial level and `set_dtr()` controls
+    /// the signal directly.
+    pub fn set_dtr_mode(&mut self, mode: DtrMode) {
+        let bits = match mode {
+            DtrMode::Disable => DTR_CONTROL_DISABLE,
+            DtrMode::Enable => DTR_CONTROL_ENABLE,
+            DtrMode::Handshake => DTR_CONTROL_HANDSHAKE
         };
 
-        if unsafe { SetCommTimeouts(self.handle, &timeouts) } == 0 {
-            return Err(super::error::last_os_error());
-        }
-
-        self.timeout = timeout;
-        Ok(())
+        self.inner.fBits = (self.inner.fBits & !fDtrControl) | bits;
     }
+}
 
-    fn set_rts(&mut self, level: bool) -> ::Result<()> {
-        if level {
-            self.escape_comm_function(SETRTS)
-        }
-        else {
-            self.escape_comm_function(CLRRTS)
-        }
-    }
+/// A builder for `COMSettings`.
+///
+/// A builder is created with [`COMSettings::builder()`](struct.COMSettings.html#method.builder).
+#[derive(Copy,Clone,Debug)]
+pub struct COMSettingsBuilder {
+    settings: PortSettings
+}
 
-    fn set_dtr(&mut self, level: bool) -> ::Result<()> {
-        if level {
-            self.escape_comm_function(SETDTR)
-        }
-        else {
-            self.escape_comm_function(CLRDTR)
-        }
+impl COMSettingsBuilder {
+    /// Sets the baud rate.
+    pub fn baud_rate(mut self, baud_rate: ::BaudRate) -> Self {
+        self.settings.baud_rate = baud_rate;
+        self
     }
 
-    fn read_cts(&mut self) -> ::Result<bool> {
-        self.read_pin(MS_CTS_ON)
+    /// Sets the character size.
+    pub fn char_size(mut self, char_size: ::CharSize) -> Self {
+        self.settings.char_size = char_size;
+        self
     }
 
-    fn read_dsr(&mut self) -> ::Result<bool> {
-        self.read_pin(MS_DSR_ON)
+    /// Sets the parity-checking mode.
+    pub fn parity(mut self, parity: ::Parity) -> Self {
+        self.settings.parity = parity;
+        self
     }
 
-    fn read_ri(&mut self) -> ::Result<bool> {
-        self.read_pin(MS_RING_ON)
+    /// Sets the number of stop bits.
+    pub fn stop_bits(mut self, stop_bits: ::StopBits) -> Self {
+        self.settings.stop_bits = stop_bits;
+        self
     }
 
-    fn read_cd(&mut self) -> ::Result<bool> {
-        self.read_pin(MS_RLSD_ON)
+    /// Sets the flow control mode.
+    pub fn flow_control(mut self, flow_control: ::FlowControl) -> Self {
+        self.settings.flow_control = flow_control;
+        self
     }
-}
 
+    /// Creates the `COMSettings`.
+    ///
+    /// ## Errors
+    ///
+    /// * `InvalidInput` if one of the settings is not supported for COM ports.
+    pub fn build(self) -> ::Result<COMSettings> {
+        let mut dcb = DCB::new();
+        dcb.fBits = fBinary | DTR_CONTROL_ENABLE | RTS_CONTROL_ENABLE;
+        dcb.XonLim = 2048;
+        dcb.XoffLim = 512;
+        dcb.XonChar = 0x11;
+        dcb.XoffChar = 0x13;
 
-/// Serial port settings for COM ports.
-#[derive(Copy,Clone,Debug)]
-pub struct COMSettings {
-    inner: DCB
+        let mut settings = COMSettings { inner: dcb };
+
+        try!(settings.set_baud_rate(self.settings.baud_rate));
+        settings.set_char_size(self.settings.char_size);
+        settings.set_parity(self.settings.parity);
+        settings.set_stop_bits(self.settings.stop_bits);
+        settings.set_flow_control(self.settings.flow_control);
+
+        Ok(settings)
+    }
 }
 
 impl SerialPortSettings for COMSettings {
     fn baud_rate(&self) -> Option<::BaudRate> {
-        match self.inner.BaudRate {
-            CBR_110    => Some(::Baud110),
-            CBR_300    => Some(::Baud300),
-            CBR_600    => Some(::Baud600),
-            CBR_1200   => Some(::Baud1200),
-            CBR_2400   => Some(::Baud2400),
-            CBR_4800   => Some(::Baud4800),
-            CBR_9600   => Some(::Baud9600),
-            CBR_14400  => Some(::BaudOther(14400)),
-            CBR_19200  => Some(::Baud19200),
-            CBR_38400  => Some(::Baud38400),
-            CBR_56000  => Some(::BaudOther(56000)),
-            CBR_57600  => Some(::Baud57600),
-            CBR_115200 => Some(::Baud115200),
-            CBR_128000 => Some(::BaudOther(128000)),
-            CBR_256000 => Some(::BaudOther(256000)),
-            n          => Some(::BaudOther(n as usize))
-        }
+        Some(::BaudRate::from_speed(self.inner.BaudRate as usize))
     }
 
     fn char_size(&self) -> Option<::CharSize> {
-        match self.inner.ByteSize {
-            5 => Some(::Bits5),
-            6 => Some(::Bits6),
-            7 => Some(::Bits7),
-            8 => Some(::Bits8),
-            _ => None
-        }
+        ::CharSize::from_bits(self.inner.ByteSize).ok()
     }
 
     fn parity(&self) -> Option<::Parity> {
         match self.inner.Parity {
-            ODDPARITY  => Some(::ParityOdd),
-            EVENPARITY => Some(::ParityEven),
-            NOPARITY   => Some(::ParityNone),
-            _          => None
+            ODDPARITY   => Some(::ParityOdd),
+            EVENPARITY  => Some(::ParityEven),
+            MARKPARITY  => Some(::ParityMark),
+            SPACEPARITY => Some(::ParitySpace),
+            NOPARITY    => Some(::ParityNone),
+            _           => None
         }
     }
 
     fn stop_bits(&self) -> Option<::StopBits> {
         match self.inner.StopBits {
-            TWOSTOPBITS => Some(::Stop2),
-            ONESTOPBIT  => Some(::Stop1),
-            _           => None
+            TWOSTOPBITS  => Some(::Stop2),
+            ONE5STOPBITS => Some(::Stop1_5),
+            ONESTOPBIT   => Some(::Stop1),
+            _            => None
         }
     }
 
     fn flow_control(&self) -> Option<::FlowControl> {
-        if self.inner.fBits & (fOutxCtsFlow | fRtsControl) != 0 {
-            Some(::FlowHardware)
-        }
-        else if self.inner.fBits & (fOutX | fInX) != 0 {
-            Some(::FlowSoftware)
+        let bits = self.inner.fBits;
+
+        // DTR/DSR handshaking has no FlowControl equivalent
+        if bits & fOutxDsrFlow != 0 || bits & fDtrControl == DTR_CONTROL_HANDSHAKE {
+            return None;
         }
-        else {
-            Some(::FlowNone)
+
+        // fRtsControl is a two-bit field, so RTS_CONTROL_TOGGLE shares bits with
+        // RTS_CONTROL_HANDSHAKE and must be compared as a whole
+        let cts = bits & fOutxCtsFlow != 0;
+        let rts = bits & fRtsControl == RTS_CONTROL_HANDSHAKE;
+
+        match (cts, rts, bits & (fOutX | fInX)) {
+            (false, false, 0) => Some(::FlowNone),
+            (false, false, xon) if xon == fOutX | fInX => Some(::FlowSoftware),
+            (true, true, 0) => Some(::FlowHardware),
+            _ => None
         }
     }
 
     fn set_baud_rate(&mut self, baud_rate: ::BaudRate) -> ::Result<()> {
-        self.inner.BaudRate = match baud_rate {
-            ::Baud110      => CBR_110,
-            ::Baud300      => CBR_300,
-            ::Baud600      => CBR_600,
-            ::Baud1200     => CBR_1200,
-            ::Baud2400     => CBR_2400,
-            ::Baud4800     => CBR_4800,
-            ::Baud9600     => CBR_9600,
-            ::Baud19200    => CBR_19200,
-            ::Baud38400    => CBR_38400,
-            ::Baud57600    => CBR_57600,
-            ::Baud115200   => CBR_115200,
-            ::BaudOther(n) => n as DWORD
-        };
+        let speed = baud_rate.speed();
+
+        // the DCB stores the baud rate in a DWORD
+        if speed > DWORD::MAX as usize {
+            return Err(::Error::new(0, ::ErrorKind::InvalidInput, format!("baud rate {} is not supported for COM ports", speed)));
+        }
 
+        self.inner.BaudRate = speed as DWORD;
         Ok(())
     }
 
     fn set_char_size(&mut self, char_size: ::CharSize) {
-        self.inner.ByteSize = match char_size {
-            ::Bits5 => 5,
-            ::Bits6 => 6,
-            ::Bits7 => 7,
-            ::Bits8 => 8
-        }
+        self.inner.ByteSize = char_size.bits();
     }
 
     fn set_parity(&mut self, parity: ::Parity) {
         self.inner.Parity = match parity {
-            ::ParityNone => NOPARITY,
-            ::ParityOdd  => ODDPARITY,
-            ::ParityEven => EVENPARITY
+            ::ParityNone  => NOPARITY,
+            ::ParityOdd   => ODDPARITY,
+            ::ParityEven  => EVENPARITY,
+            ::ParityMark  => MARKPARITY,
+            ::ParitySpace => SPACEPARITY
         }
     }
 
     fn set_stop_bits(&mut self, stop_bits: ::StopBits) {
         self.inner.StopBits = match stop_bits {
-            ::Stop1 => ONESTOPBIT,
-            ::Stop2 => TWOSTOPBITS
+            ::Stop1   => ONESTOPBIT,
+            ::Stop1_5 => ONE5STOPBITS,
+            ::Stop2   => TWOSTOPBITS
         }
     }
 
     fn set_flow_control(&mut self, flow_control: ::FlowControl) {
         match flow_control {
             ::FlowNone => {
-                self.inner.fBits &= !(fOutxCtsFlow | fRtsControl);
+                self.set_handshake(Handshake::default());
                 self.inner.fBits &= !(fOutX | fInX);
             },
             ::FlowSoftware => {
-                self.inner.fBits &= !(fOutxCtsFlow | fRtsControl);
+                self.set_handshake(Handshake::default());
                 self.inner.fBits |= fOutX | fInX;
             },
             ::FlowHardware => {
-                self.inner.fBits |= fOutxCtsFlow | fRtsControl;
+                self.set_handshake(Handshake { rts_cts: true, dtr_dsr: false });
                 self.inner.fBits &= !(fOutX | fInX);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::SerialPortSettings;
+
+    #[test]
+    fn com_port_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<COMPort>();
+    }
+
+    #[test]
+    fn shared_com_port_is_read_and_write() {
+        fn assert_read_write<T: io::Read + io::Write>() {}
+        assert_read_write::<&COMPort>();
+    }
+
+    #[test]
+    fn com_port_converts_to_stdio() {
+        fn assert_into_stdio<T: Into<process::Stdio>>() {}
+        assert_into_stdio::<COMPort>();
+    }
+
+    #[test]
+    fn opening_nonexistent_port_reports_no_device() {
+        let err = COMPort::open("COM_DOES_NOT_EXIST").unwrap_err();
+        assert_eq!(err.kind(), ::ErrorKind::NoDevice);
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(Some(0)).collect()
+    }
+
+    #[test]
+    fn device_path_prefixes_single_digit_port() {
+        assert_eq!(device_path(OsStr::new("COM9")), wide("\\\\.\\COM9"));
+    }
+
+    #[test]
+    fn device_path_prefixes_double_digit_port() {
+        assert_eq!(device_path(OsStr::new("COM10")), wide("\\\\.\\COM10"));
+    }
+
+    #[test]
+    fn device_path_does_not_double_prefix() {
+        assert_eq!(device_path(OsStr::new("\\\\.\\COM10")), wide("\\\\.\\COM10"));
+    }
+
+    #[test]
+    fn com_settings_reads_back_non_standard_baud_rate() {
+        let settings = COMSettings::builder().baud_rate(::BaudOther(250000)).build().unwrap();
+        assert_eq!(settings.baud_rate(), Some(::BaudOther(250000)));
+    }
+
+    #[test]
+    fn com_settings_reads_back_standard_baud_rate() {
+        let settings = COMSettings::builder().baud_rate(::Baud115200).build().unwrap();
+        assert_eq!(settings.baud_rate(), Some(::Baud115200));
+    }
+
+    #[test]
+    fn com_settings_reads_back_windows_only_baud_rate() {
+        let settings = COMSettings::builder().baud_rate(::BaudOther(14400)).build().unwrap();
+        assert_eq!(settings.inner.BaudRate, CBR_14400);
+        assert_eq!(settings.baud_rate(), Some(::BaudOther(14400)));
+    }
+
+    #[test]
+    fn com_settings_manipulates_flow_control_chars() {
+        let mut settings = COMSettings::builder().build().unwrap();
+        settings.set_flow_control_chars(0xf1, 0xf3);
+        assert_eq!(settings.flow_control_chars(), (0xf1, 0xf3));
+    }
+
+    #[test]
+    fn zero_read_timeout_returns_immediately() {
+        let timeouts = Timeouts { read: Duration::from_millis(0), ..Timeouts::default() };
+        let comm_timeouts = timeouts.to_comm_timeouts();
+
+        assert_eq!(comm_timeouts.ReadIntervalTimeout, MAXDWORD);
+        assert_eq!(comm_timeouts.ReadTotalTimeoutConstant, 0);
+        assert_eq!(comm_timeouts.ReadTotalTimeoutMultiplier, 0);
+    }
+
+    #[test]
+    fn zero_event_timeout_checks_without_blocking() {
+        // WaitForSingleObject returns immediately for a timeout of zero milliseconds
+        assert_eq!(duration_to_millis(Duration::from_millis(0)), 0);
+    }
+
+    #[test]
+    fn sub_millisecond_timeouts_are_not_infinite() {
+        let short = Duration::new(0, 500_000);
+        let timeouts = Timeouts { read: short, write: short, ..Timeouts::default() };
+        let comm_timeouts = timeouts.to_comm_timeouts();
+
+        assert_eq!(comm_timeouts.ReadTotalTimeoutConstant, 1);
+        assert_eq!(comm_timeouts.WriteTotalTimeoutConstant, 1);
+        assert_eq!(duration_to_millis(Duration::new(1, 1)), 1001);
+    }
+
+    #[test]
+    fn long_timeouts_saturate() {
+        assert_eq!(duration_to_millis(Duration::from_secs(4_294_968)), MAXDWORD - 1);
+        assert_eq!(duration_to_millis(Duration::from_millis(1 << 32)), MAXDWORD - 1);
+        assert_eq!(duration_to_millis(Duration::new(u64::MAX, 999_999_999)), MAXDWORD - 1);
+        assert_eq!(duration_to_millis(Duration::from_millis(MAXDWORD as u64 - 1)), MAXDWORD - 1);
+    }
+
+    #[test]
+    fn baud_error_is_percentage_of_requested_rate() {
+        assert_eq!(baud_error(250000, 248000), Some(-0.8));
+        assert_eq!(baud_error(9600, 9600), Some(0.0));
+        assert_eq!(baud_error(0, 9600), None);
+    }
+
+    #[test]
+    fn com_settings_presets_set_framing() {
+        let standard = COMSettings::standard(::Baud115200).unwrap();
+        assert!(standard.is_8n1());
+        assert_eq!(standard.baud_rate(), Some(::Baud115200));
+
+        let seven_e_1 = COMSettings::seven_e_1(::Baud9600).unwrap();
+        assert!(!seven_e_1.is_8n1());
+        assert_eq!(seven_e_1.char_size(), Some(::Bits7));
+        assert_eq!(seven_e_1.parity(), Some(::ParityEven));
+        assert_eq!(seven_e_1.stop_bits(), Some(::Stop1));
+    }
+
+    #[test]
+    fn com_settings_reports_foreign_char_size_as_error() {
+        let mut settings = COMSettings::builder().build().unwrap();
+        settings.inner.ByteSize = 4;
+
+        assert_eq!(settings.char_size(), None);
+        assert_eq!(settings.try_char_size().unwrap_err().kind(), ::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn com_settings_sets_handshakes_independently() {
+        let mut settings = COMSettings::builder().build().unwrap();
+        settings.set_handshake(Handshake { rts_cts: false, dtr_dsr: true });
+        assert_eq!(settings.handshake(), Handshake { rts_cts: false, dtr_dsr: true });
+        assert_eq!(settings.inner.fBits & fRtsControl, RTS_CONTROL_ENABLE);
+        assert_eq!(settings.inner.fBits & fDtrControl, DTR_CONTROL_HANDSHAKE);
+    }
+
+    #[test]
+    fn com_settings_sets_control_signal_modes() {
+        let mut settings = COMSettings::builder().build().unwrap();
+        assert_eq!(settings.rts_mode(), RtsMode::Enable);
+
+        settings.set_rts_mode(RtsMode::Toggle);
+        settings.set_dtr_mode(DtrMode::Disable);
+        assert_eq!(settings.rts_mode(), RtsMode::Toggle);
+        assert_eq!(settings.dtr_mode(), DtrMode::Disable);
+        assert_eq!(settings.inner.fBits & fRtsControl, RTS_CONTROL_TOGGLE);
+        assert_eq!(settings.inner.fBits & fDtrControl, DTR_CONTROL_DISABLE);
+    }
+
+    #[test]
+    fn com_settings_restores_control_signals_when_disabling_handshake() {
+        let mut settings = COMSettings::builder().flow_control(::FlowHardware).build().unwrap();
+        assert_eq!(settings.inner.fBits & fRtsControl, RTS_CONTROL_HANDSHAKE);
+
+        settings.set_flow_control(::FlowNone);
+        assert_eq!(settings.handshake(), Handshake::default());
+        assert_eq!(settings.inner.fBits & fRtsControl, RTS_CONTROL_ENABLE);
+    }
+
+    #[test]
+    fn com_settings_reads_back_each_flow_control_mode() {
+        for &flow_control in &[::FlowNone, ::FlowSoftware, ::FlowHardware] {
+            let settings = COMSettings::builder().flow_control(flow_control).build().unwrap();
+            assert_eq!(settings.flow_control(), Some(flow_control));
+        }
+    }
+
+    #[test]
+    fn com_settings_reads_back_flow_control_after_changing_modes() {
+        let mut settings = COMSettings::builder().flow_control(::FlowHardware).build().unwrap();
+        settings.set_flow_control(::FlowSoftware);
+        assert_eq!(settings.flow_control(), Some(::FlowSoftware));
+
+        settings.set_flow_control(::FlowHardware);
+        assert_eq!(settings.flow_control(), Some(::FlowHardware));
+    }
+
+    #[test]
+    fn com_settings_does_not_report_rts_toggle_as_hardware_flow_control() {
+        let mut settings = COMSettings::builder().flow_control(::FlowSoftware).build().unwrap();
+        settings.inner.fBits = (settings.inner.fBits & !fRtsControl) | RTS_CONTROL_TOGGLE;
+        assert_eq!(settings.flow_control(), Some(::FlowSoftware));
+
+        settings.inner.fBits &= !fRtsControl;
+        assert_eq!(settings.flow_control(), Some(::FlowSoftware));
+    }
+
+    #[test]
+    fn com_settings_does_not_report_dtr_dsr_handshake_as_flow_control() {
+        let mut settings = COMSettings::builder().build().unwrap();
+        settings.set_handshake(Handshake { rts_cts: false, dtr_dsr: true });
+        assert_eq!(settings.flow_control(), None);
+    }
+
+    #[test]
+    fn com_settings_computes_frame_duration() {
+        let settings = COMSettings::builder().baud_rate(::Baud9600).build().unwrap();
+        assert_eq!(settings.frame_duration(), Some(Duration::new(0, 1_041_666)));
+
+        let settings = COMSettings::builder()
+            .baud_rate(::Baud1200)
+            .char_size(::Bits7)
+            .parity(::ParityEven)
+            .stop_bits(::Stop1_5)
+            .build()
+            .unwrap();
+        assert_eq!(settings.frame_duration(), Some(Duration::new(0, 8_750_000)));
+
+        let settings = COMSettings::builder().baud_rate(::BaudOther(0)).build().unwrap();
+        assert_eq!(settings.frame_duration(), None);
+    }
+
+    #[test]
+    fn rs485_write_reports_written_bytes_and_holds_later_failure() {
+        let failure = ::Error::new(0, ::ErrorKind::Disconnected, "device was removed");
+
+        let (result, held) = finish_rs485_write(Ok(5), Err(failure));
+        assert_eq!(result.unwrap(), 5);
+        assert_eq!(held.unwrap().kind(), io::ErrorKind::NotConnected);
+
+        let (result, held) = finish_rs485_write(Ok(5), Ok(()));
+        assert_eq!(result.unwrap(), 5);
+        assert!(held.is_none());
+    }
+
+    #[test]
+    fn rs485_write_reports_write_failure() {
+        let written = Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+        let failure = ::Error::new(0, ::ErrorKind::Io(io::ErrorKind::Other), "RTS could not be set");
+
+        let (result, held) = finish_rs485_write(written, Err(failure));
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(held.is_none());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn com_settings_rejects_baud_rates_beyond_dword() {
+        let mut settings = COMSettings::builder().build().unwrap();
+
+        let result = settings.set_baud_rate(::BaudOther(DWORD::MAX as usize + 1));
+        assert_eq!(result.unwrap_err().kind(), ::ErrorKind::InvalidInput);
+        assert_eq!(settings.baud_rate(), Some(::Baud9600));
+
+        let result = COMSettings::builder().baud_rate(::BaudOther(DWORD::MAX as usize + 1)).build();
+        assert_eq!(result.unwrap_err().kind(), ::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn com_settings_sets_error_char_and_null_stripping() {
+        let mut settings = COMSettings::builder().parity(::ParityEven).build().unwrap();
+        assert_eq!(settings.error_char(), None);
+        assert!(!settings.null_stripping());
+
+        settings.set_parity_check(true);
+        settings.set_error_char(Some(b'?'));
+        settings.set_null_stripping(true);
+        assert_eq!(settings.error_char(), Some(b'?'));
+        assert_eq!(settings.inner.fBits & (fParity | fErrorChar | fNull), fParity | fErrorChar | fNull);
+
+        settings.set_error_char(None);
+        assert_eq!(settings.error_char(), None);
+    }
+
+    #[test]
+    fn com_settings_sets_dsr_sensitivity_and_tx_continue_on_xoff() {
+        let mut settings = COMSettings::builder().build().unwrap();
+        settings.set_dsr_sensitivity(true);
+        settings.set_tx_continue_on_xoff(true);
+        assert!(settings.dsr_sensitivity());
+        assert!(settings.tx_continue_on_xoff());
+
+        settings.set_dsr_sensitivity(false);
+        assert!(!settings.dsr_sensitivity());
+        assert_eq!(settings.inner.fBits & (fDsrSensitivity | fTXContinueOnXoff), fTXContinueOnXoff);
+    }
+
+    #[test]
+    fn com_settings_are_binary_transparent_by_default() {
+        for settings in &[COMSettings::builder().build().unwrap(), COMSettings::standard(::Baud115200).unwrap()] {
+            assert!(settings.inner.fBits & fBinary != 0);
+            assert_eq!(settings.inner.fBits & (fNull | fErrorChar), 0);
+        }
+    }
+
+    #[test]
+    fn com_settings_manipulates_abort_on_error() {
+        let mut settings = COMSettings::builder().build().unwrap();
+
+        settings.set_abort_on_error(true);
+        assert!(settings.abort_on_error());
+
+        settings.set_abort_on_error(false);
+        assert!(!settings.abort_on_error());
+        assert_eq!(settings.inner.fBits & fAbortOnError, 0);
+        assert!(settings.inner.fBits & fBinary != 0);
+    }
+
+    #[test]
+    fn com_settings_compares_logical_settings() {
+        let settings = COMSettings::builder().baud_rate(::Baud19200).flow_control(::FlowHardware).build().unwrap();
+
+        let mut applied = settings;
+        applied.set_flow_control_chars(0x01, 0x02);
+        assert_eq!(applied, settings);
+
+        applied.set_flow_control(::FlowSoftware);
+        assert!(applied != settings);
+
+        let mut applied = settings;
+        applied.inner.BaudRate = 19231;
+        assert!(applied != settings);
+    }
+
+    #[test]
+    fn comm_properties_decodes_raw_properties() {
+        let raw = LPCOMMPROP {
+            dwMaxBaud: BAUD_115200,
+            dwMaxTxQueue: 0,
+            dwMaxRxQueue: 4096,
+            dwSettableBaud: BAUD_9600 | BAUD_115200,
+            dwSettableParams: SP_BAUD | SP_PARITY,
+            ..LPCOMMPROP::default()
+        };
+
+        let props = CommProperties::from_raw(&raw);
+        assert_eq!(props.max_baud, Some(::Baud115200));
+        assert_eq!(props.max_tx_queue, None);
+        assert_eq!(props.max_rx_queue, Some(4096));
+        assert!(props.settable_baud.contains(::Baud9600));
+        assert!(!props.settable_baud.contains(::Baud19200));
+        assert_eq!(props.settable_params, SettableParams { baud_rate: true, parity: true, ..SettableParams::default() });
+    }
+
+    #[test]
+    fn capabilities_lists_supported_baud_rates_in_order() {
+        let raw = LPCOMMPROP {
+            wPacketLength: 64,
+            dwMaxBaud: BAUD_115200,
+            dwSettableBaud: BAUD_115200 | BAUD_9600 | BAUD_57600,
+            ..LPCOMMPROP::default()
+        };
+
+        let caps = Capabilities::from_raw(&raw);
+        assert_eq!(caps.max_baud, 115200);
+        assert_eq!(caps.supported_baud_rates, vec![::Baud9600, ::Baud57600, ::Baud115200]);
+        assert!(!caps.programmable_baud);
+        assert_eq!(caps.packet_length, 64);
+    }
+
+    #[test]
+    fn comm_properties_reports_programmable_baud_rates() {
+        let raw = LPCOMMPROP { dwMaxBaud: BAUD_USER, dwSettableBaud: BAUD_USER, ..LPCOMMPROP::default() };
+
+        let props = CommProperties::from_raw(&raw);
+        assert_eq!(props.max_baud, None);
+        assert!(props.settable_baud.contains(::BaudOther(250000)));
+    }
+
+    #[test]
+    fn event_mask_round_trips_through_bits() {
+        let mask = EventMask { rx_char: true, ring: true, error: true, ..EventMask::default() };
+        assert_eq!(mask.to_bits(), EV_RXCHAR | EV_RING | EV_ERR);
+        assert_eq!(EventMask::from_bits(mask.to_bits()), mask);
+    }
+
+    #[test]
+    fn polled_status_reports_changes_since_previous_poll() {
+        let previous = PolledStatus { bytes_in: 4, modem: MS_CTS_ON, errors: 0 };
+
+        assert_eq!(previous.events_since(&previous), 0);
+        assert_eq!(PolledStatus { bytes_in: 3, ..previous }.events_since(&previous), 0);
+        assert_eq!(PolledStatus { bytes_in: 5, ..previous }.events_since(&previous), EV_RXCHAR);
+        assert_eq!(PolledStatus { modem: MS_DSR_ON, ..previous }.events_since(&previous), EV_CTS | EV_DSR);
+        assert_eq!(PolledStatus { errors: CE_BREAK | CE_FRAME, ..previous }.events_since(&previous), EV_BREAK | EV_ERR);
+        assert_eq!(PolledStatus { errors: CE_RXOVER, ..previous }.events_since(&previous), 0);
+    }
+
+    #[test]
+    fn line_errors_decodes_status_bits() {
+        let errors = LineErrors::from_bits(CE_FRAME | CE_OVERRUN);
+        assert_eq!(errors, LineErrors { framing: true, overrun: true, ..LineErrors::default() });
+        assert!(errors.any());
+    }
+}
@@ -0,0 +1,303 @@
+use std::ffi::OsStr;
+use std::mem;
+use std::ptr;
+
+use std::os::windows::prelude::*;
+
+use super::ffi::*;
+use ::{PortInfo,SerialPortType,UsbPortInfo};
+
+
+/// Lists the COM ports that are present on the system.
+///
+/// Ports are discovered through the device setup class for serial ports. The USB vendor and
+/// product IDs are parsed from the device instance ID when the port belongs to a USB device, and
+/// the kind of hardware is determined from the bus that the device is attached to.
+pub fn available_ports() -> ::Result<Vec<PortInfo>> {
+    ports().collect()
+}
+
+/// Returns an iterator over the COM ports that are present on the system.
+///
+/// Ports are discovered the same way as by `available_ports()`, but one at a time as the iterator
+/// advances, so enumeration can stop as soon as the wanted port has been found.
+pub fn ports() -> Ports {
+    let devices = unsafe {
+        SetupDiGetClassDevsW(&GUID_DEVCLASS_PORTS, ptr::null(), ptr::null_mut(), DIGCF_PRESENT)
+    };
+
+    let error = if devices == INVALID_HANDLE_VALUE {
+        Some(super::error::last_os_error())
+    }
+    else {
+        None
+    };
+
+    Ports {
+        devices: devices,
+        index: 0,
+        error: error
+    }
+}
+
+/// Checks whether a COM port with the given name is present, without opening it.
+///
+/// The name is looked up among the system's MS-DOS device names, which include a COM port for as
+/// long as its device is present. A `\\.\` prefix is ignored.
+pub fn port_exists(port: &OsStr) -> bool {
+    let prefix: Vec<u16> = OsStr::new("\\\\.\\").encode_wide().collect();
+    let mut name: Vec<u16> = port.encode_wide().collect();
+
+    if name.starts_with(&prefix) {
+        name.drain(..prefix.len());
+    }
+
+    if name.is_empty() || name.contains(&0) {
+        return false;
+    }
+
+    name.push(0);
+
+    // only whether the name is defined matters, so a target that doesn't fit still counts
+    let mut target = [0u16; 256];
+    let len = unsafe { QueryDosDeviceW(name.as_ptr(), target.as_mut_ptr(), target.len() as DWORD) };
+
+    len != 0 || unsafe { GetLastError() } == ERROR_INSUFFICIENT_BUFFER
+}
+
+/// An iterator over the COM ports that are present on the system.
+///
+/// The iterator is returned by [`ports()`](fn.ports.html). If the system's device list can't be
+/// read, the iterator yields a single error.
+#[derive(Debug)]
+pub struct Ports {
+    devices: HDEVINFO,
+    index: DWORD,
+    error: Option<::Error>
+}
+
+impl Ports {
+    fn close(&mut self) {
+        if self.devices != INVALID_HANDLE_VALUE {
+            unsafe { SetupDiDestroyDeviceInfoList(self.devices) };
+            self.devices = INVALID_HANDLE_VALUE;
+        }
+    }
+}
+
+impl Iterator for Ports {
+    type Item = ::Result<PortInfo>;
+
+    fn next(&mut self) -> Option<::Result<PortInfo>> {
+        loop {
+            if self.devices == INVALID_HANDLE_VALUE {
+                return self.error.take().map(Err);
+            }
+
+            let mut data = SP_DEVINFO_DATA::new();
+
+            if unsafe { SetupDiEnumDeviceInfo(self.devices, self.index, &mut data) } == 0 {
+                let err = super::error::last_os_error();
+                self.close();
+
+                return match err.raw_os_error() {
+                    Some(code) if code as DWORD == ERROR_NO_MORE_ITEMS => None,
+                    _ => Some(Err(err))
+                };
+            }
+
+            self.index += 1;
+
+            if let Some(port_name) = port_name(self.devices, &mut data) {
+                if !port_name.starts_with("COM") {
+                    continue;
+                }
+
+                let (vid, pid, port_type) = match instance_id(self.devices, &mut data) {
+                    Some(id) => {
+                        let (vid, pid) = usb_ids(&id);
+                        let manufacturer = registry_property(self.devices, &mut data, SPDRP_MFG);
+                        let product = registry_property(self.devices, &mut data, SPDRP_DEVICEDESC);
+
+                        (vid, pid, port_type(&id, manufacturer, product))
+                    },
+                    None => (None, None, SerialPortType::Unknown)
+                };
+
+                return Some(Ok(PortInfo {
+                    port_name: port_name,
+                    vid: vid,
+                    pid: pid,
+                    port_type: port_type
+                }));
+            }
+        }
+    }
+}
+
+impl Drop for Ports {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn port_name(devices: HDEVINFO, data: &mut SP_DEVINFO_DATA) -> Option<String> {
+    let key = unsafe { SetupDiOpenDevRegKey(devices, data, DICS_FLAG_GLOBAL, 0, DIREG_DEV, KEY_READ) };
+
+    if key == INVALID_HANDLE_VALUE {
+        return None;
+    }
+
+    let mut value_name: Vec<u16> = OsStr::new("PortName").encode_wide().collect();
+    value_name.push(0);
+
+    let mut buf = [0 as WCHAR; 256];
+    let mut buf_len = mem::size_of_val(&buf) as DWORD;
+    let mut value_type: DWORD = 0;
+
+    let res = unsafe {
+        RegQueryValueExW(key, value_name.as_ptr(), ptr::null_mut(), &mut value_type, buf.as_mut_ptr() as *mut BYTE, &mut buf_len)
+    };
+
+    unsafe { RegCloseKey(key) };
+
+    if res != ERROR_SUCCESS || value_type != REG_SZ {
+        return None;
+    }
+
+    let len = buf_len as usize / mem::size_of::<WCHAR>();
+    from_wide(&buf[..len])
+}
+
+fn instance_id(devices: HDEVINFO, data: &mut SP_DEVINFO_DATA) -> Option<String> {
+    let mut buf = [0 as WCHAR; 256];
+
+    match unsafe { SetupDiGetDeviceInstanceIdW(devices, data, buf.as_mut_ptr(), buf.len() as DWORD, ptr::null_mut()) } {
+        0 => None,
+        _ => from_wide(&buf)
+    }
+}
+
+fn registry_property(devices: HDEVINFO, data: &mut SP_DEVINFO_DATA, property: DWORD) -> Option<String> {
+    let mut buf = [0 as WCHAR; 256];
+    let mut value_type: DWORD = 0;
+
+    let res = unsafe {
+        SetupDiGetDeviceRegistryPropertyW(devices, data, property, &mut value_type, buf.as_mut_ptr() as *mut BYTE, mem::size_of_val(&buf) as DWORD, ptr::null_mut())
+    };
+
+    if res == 0 || value_type != REG_SZ {
+        return None;
+    }
+
+    from_wide(&buf)
+}
+
+fn from_wide(buf: &[WCHAR]) -> Option<String> {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16(&buf[..len]).ok()
+}
+
+/// Parses the USB vendor and product IDs out of a device instance ID.
+///
+/// USB instance IDs take the form `USB\VID_2341&PID_0043\...`.
+fn usb_ids(instance_id: &str) -> (Option<u16>, Option<u16>) {
+    let id = instance_id.to_uppercase();
+
+    (hex_field(&id, "VID_"), hex_field(&id, "PID_"))
+}
+
+/// Classifies a port by the bus in its device instance ID, e.g., `PCI\...` or `BTHENUM\...`.
+fn port_type(instance_id: &str, manufacturer: Option<String>, product: Option<String>) -> SerialPortType {
+    if let (Some(vid), Some(pid)) = usb_ids(instance_id) {
+        return SerialPortType::UsbPort(UsbPortInfo {
+            vid: vid,
+            pid: pid,
+            serial_number: usb_serial_number(instance_id),
+            manufacturer: manufacturer,
+            product: product
+        });
+    }
+
+    let bus = instance_id.split('\\').next().unwrap_or("").to_uppercase();
+
+    match &bus[..] {
+        "PCI" | "ACPI" => SerialPortType::PciPort,
+        "BTHENUM" => SerialPortType::BluetoothPort,
+        _ => SerialPortType::Unknown
+    }
+}
+
+/// Parses the serial number of a USB device out of its device instance ID.
+///
+/// For devices with a serial number, the last part of a USB instance ID is the serial number,
+/// e.g., `USB\VID_2341&PID_0043\75830303934351D0E1A0`. Windows generates an ID containing `&` for
+/// devices without one. The FTDI driver puts the serial number in the middle part instead, with
+/// the letter of the channel appended, e.g., `FTDIBUS\VID_0403+PID_6001+A600B1TJA\0000`.
+fn usb_serial_number(instance_id: &str) -> Option<String> {
+    let parts: Vec<&str> = instance_id.split('\\').collect();
+
+    if parts.len() != 3 {
+        return None;
+    }
+
+    match &parts[0].to_uppercase()[..] {
+        "USB" if !parts[2].contains('&') => Some(parts[2].to_string()),
+        "FTDIBUS" => {
+            parts[1].split('+').nth(2)
+                .filter(|serial| serial.len() > 1)
+                .map(|serial| serial[..serial.len() - 1].to_string())
+        },
+        _ => None
+    }
+}
+
+fn hex_field(id: &str, prefix: &str) -> Option<u16> {
+    id.find(prefix)
+        .and_then(|start| id.get(start + prefix.len()..start + prefix.len() + 4))
+        .and_then(|digits| u16::from_str_radix(digits, 16).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{usb_ids,usb_serial_number,port_type};
+    use ::{SerialPortType,UsbPortInfo};
+
+    #[test]
+    fn usb_ids_parses_usb_instance_id() {
+        assert_eq!(usb_ids("USB\\VID_2341&PID_0043\\75830303934351D0E1A0"), (Some(0x2341), Some(0x0043)));
+    }
+
+    #[test]
+    fn usb_ids_parses_ftdi_instance_id() {
+        assert_eq!(usb_ids("FTDIBUS\\VID_0403+PID_6001+A600B1TJA\\0000"), (Some(0x0403), Some(0x6001)));
+    }
+
+    #[test]
+    fn usb_ids_ignores_non_usb_instance_id() {
+        assert_eq!(usb_ids("ACPI\\PNP0501\\1"), (None, None));
+    }
+
+    #[test]
+    fn usb_serial_number_parses_usb_and_ftdi_instance_ids() {
+        assert_eq!(usb_serial_number("USB\\VID_2341&PID_0043\\75830303934351D0E1A0"), Some("75830303934351D0E1A0".to_string()));
+        assert_eq!(usb_serial_number("FTDIBUS\\VID_0403+PID_6001+A600B1TJA\\0000"), Some("A600B1TJ".to_string()));
+        assert_eq!(usb_serial_number("USB\\VID_1A86&PID_7523\\5&2D2B1E7&0&2"), None);
+    }
+
+    #[test]
+    fn port_type_classifies_by_bus() {
+        let usb = port_type("USB\\VID_2341&PID_0043\\1234", Some("Arduino LLC".to_string()), None);
+        assert_eq!(usb, SerialPortType::UsbPort(UsbPortInfo {
+            vid: 0x2341,
+            pid: 0x0043,
+            serial_number: Some("1234".to_string()),
+            manufacturer: Some("Arduino LLC".to_string()),
+            product: None
+        }));
+
+        assert_eq!(port_type("ACPI\\PNP0501\\1", None, None), SerialPortType::PciPort);
+        assert_eq!(port_type("BTHENUM\\{00001101-0000-1000-8000-00805F9B34FB}\\7&1", None, None), SerialPortType::BluetoothPort);
+        assert_eq!(port_type("ROOT\\PORTS\\0000", None, None), SerialPortType::Unknown);
+    }
+}
@@ -8,18 +8,60 @@ use self::libc::{c_void,c_int};
 const ERROR_FILE_NOT_FOUND: c_int = 2;
 const ERROR_PATH_NOT_FOUND: c_int = 3;
 const ERROR_ACCESS_DENIED: c_int = 5;
+const ERROR_BAD_COMMAND: c_int = 22;
+const ERROR_SHARING_VIOLATION: c_int = 32;
+const ERROR_DEVICE_NOT_CONNECTED: c_int = 1167;
+const ERROR_DEVICE_REMOVED: c_int = 1617;
 
 pub fn last_os_error() -> ::Error {
-    let errno = errno();
+    from_raw_os_error(errno())
+}
 
+pub fn from_raw_os_error(errno: i32) -> ::Error {
     let kind = match errno {
-        ERROR_FILE_NOT_FOUND | ERROR_PATH_NOT_FOUND | ERROR_ACCESS_DENIED => ::ErrorKind::NoDevice,
+        ERROR_FILE_NOT_FOUND | ERROR_PATH_NOT_FOUND => ::ErrorKind::NoDevice,
+        ERROR_DEVICE_NOT_CONNECTED | ERROR_DEVICE_REMOVED => ::ErrorKind::NoDevice,
+        ERROR_ACCESS_DENIED => ::ErrorKind::PermissionDenied,
+        ERROR_SHARING_VIOLATION => ::ErrorKind::Busy,
         _ => ::ErrorKind::Io(io::ErrorKind::Other)
     };
 
     ::Error::new(errno,kind, error_string(errno).trim())
 }
 
+/// Returns the last error of an operation on a device that is already open.
+pub fn last_device_error() -> ::Error {
+    from_device_error(errno())
+}
+
+pub fn from_device_error(errno: i32) -> ::Error {
+    if is_disconnect_error(errno) {
+        ::Error::new(errno, ::ErrorKind::Disconnected, error_string(errno).trim())
+    }
+    else {
+        from_raw_os_error(errno)
+    }
+}
+
+/// Returns `true` if an error code of an operation on an open device reports that the device was
+/// removed.
+pub fn is_disconnect_error(errno: i32) -> bool {
+    match errno {
+        // an open handle keeps its access rights, so USB serial drivers that report access denied
+        // or a command the device doesn't recognize are reporting that the device was removed
+        ERROR_DEVICE_NOT_CONNECTED | ERROR_DEVICE_REMOVED | ERROR_BAD_COMMAND | ERROR_ACCESS_DENIED => true,
+        _ => false
+    }
+}
+
+/// Returns the last error of an I/O operation on a device that is already open.
+///
+/// The error keeps its OS error code, which `serial::is_disconnected()` uses to recognize a
+/// removed device.
+pub fn last_io_error() -> io::Error {
+    io::Error::from_raw_os_error(errno())
+}
+
 // the rest of this module is borrowed from libstd
 
 fn errno() -> i32 {
@@ -79,3 +121,37 @@ fn error_string(errnum: i32) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERROR_GEN_FAILURE: c_int = 31;
+
+    #[test]
+    fn from_raw_os_error_distinguishes_open_failures() {
+        assert_eq!(from_raw_os_error(ERROR_FILE_NOT_FOUND).kind(), ::ErrorKind::NoDevice);
+        assert_eq!(from_raw_os_error(ERROR_ACCESS_DENIED).kind(), ::ErrorKind::PermissionDenied);
+        assert_eq!(from_raw_os_error(ERROR_SHARING_VIOLATION).kind(), ::ErrorKind::Busy);
+    }
+
+    #[test]
+    fn from_raw_os_error_does_not_report_generic_failures_as_no_device() {
+        assert_eq!(from_raw_os_error(ERROR_BAD_COMMAND).kind(), ::ErrorKind::Io(io::ErrorKind::Other));
+        assert_eq!(from_raw_os_error(ERROR_GEN_FAILURE).kind(), ::ErrorKind::Io(io::ErrorKind::Other));
+    }
+
+    #[test]
+    fn from_device_error_reports_removal_as_disconnected() {
+        assert_eq!(from_device_error(ERROR_DEVICE_NOT_CONNECTED).kind(), ::ErrorKind::Disconnected);
+        assert_eq!(from_device_error(ERROR_BAD_COMMAND).kind(), ::ErrorKind::Disconnected);
+        assert_eq!(from_device_error(ERROR_ACCESS_DENIED).kind(), ::ErrorKind::Disconnected);
+        assert_eq!(from_device_error(ERROR_DEVICE_NOT_CONNECTED).raw_os_error(), Some(ERROR_DEVICE_NOT_CONNECTED));
+    }
+
+    #[test]
+    fn from_device_error_keeps_other_errors() {
+        assert_eq!(from_device_error(ERROR_GEN_FAILURE).kind(), ::ErrorKind::Io(io::ErrorKind::Other));
+        assert!(!is_disconnect_error(ERROR_FILE_NOT_FOUND));
+    }
+}
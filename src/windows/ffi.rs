@@ -4,12 +4,14 @@ extern crate libc;
 
 use std::mem;
 
-use self::libc::{c_void,c_char,c_int,c_ulong,wchar_t};
+use self::libc::{c_void,c_char,c_int,c_long,c_ulong,wchar_t};
 
 pub type BYTE = u8;
+pub type BOOLEAN = u8;
 pub type WORD = u16;
 pub type DWORD = c_ulong;
 pub type BOOL = c_int;
+pub type LONG = c_long;
 pub type WCHAR = wchar_t;
 
 pub type LPDWORD = *mut DWORD;
@@ -19,12 +21,19 @@ pub type LPCWSTR = *const WCHAR;
 pub type LPWSTR = *mut WCHAR;
 
 pub type HANDLE = *mut LPVOID;
+pub type HKEY = HANDLE;
+pub type HDEVINFO = HANDLE;
 
 pub const GENERIC_READ: DWORD = 0x80000000;
 pub const GENERIC_WRITE: DWORD = 0x40000000;
+pub const FILE_SHARE_READ: DWORD = 0x00000001;
+pub const FILE_SHARE_WRITE: DWORD = 0x00000002;
 pub const OPEN_EXISTING: DWORD = 3;
 pub const FILE_ATTRIBUTE_NORMAL: DWORD = 0x80;
+pub const FILE_FLAG_OVERLAPPED: DWORD = 0x40000000;
 pub const INVALID_HANDLE_VALUE: HANDLE = !0 as HANDLE;
+pub const MAXDWORD: DWORD = 0xFFFFFFFF;
+pub const DUPLICATE_SAME_ACCESS: DWORD = 0x00000002;
 
 #[repr(C)]
 pub struct SECURITY_ATTRIBUTES {
@@ -46,6 +55,18 @@ pub struct OVERLAPPED {
 
 pub type LPOVERLAPPED = *mut OVERLAPPED;
 
+// WaitForSingleObject and WaitForMultipleObjects values
+pub const INFINITE:             DWORD = 0xFFFFFFFF;
+pub const WAIT_OBJECT_0:        DWORD = 0x00000000;
+pub const WAIT_TIMEOUT:         DWORD = 0x00000102;
+pub const WAIT_FAILED:          DWORD = 0xFFFFFFFF;
+pub const MAXIMUM_WAIT_OBJECTS: DWORD = 64;
+
+// RegisterWaitForSingleObject values
+pub const WT_EXECUTEONLYONCE: DWORD = 0x00000008;
+
+pub type WAITORTIMERCALLBACK = unsafe extern "system" fn(lpParameter: LPVOID, TimerOrWaitFired: BOOLEAN);
+
 #[derive(Copy,Clone,Debug)]
 #[repr(C)]
 pub struct DCB {
@@ -99,6 +120,17 @@ pub const fRtsControl:       DWORD = 0x00003000;
 pub const fAbortOnError:     DWORD = 0x00004000;
 pub const fDummy2:           DWORD = 0xFFFF8000;
 
+// fDtrControl values, in their fBits position
+pub const DTR_CONTROL_DISABLE:   DWORD = 0x00000000;
+pub const DTR_CONTROL_ENABLE:    DWORD = 0x00000010;
+pub const DTR_CONTROL_HANDSHAKE: DWORD = 0x00000020;
+
+// fRtsControl values, in their fBits position
+pub const RTS_CONTROL_DISABLE:   DWORD = 0x00000000;
+pub const RTS_CONTROL_ENABLE:    DWORD = 0x00001000;
+pub const RTS_CONTROL_HANDSHAKE: DWORD = 0x00002000;
+pub const RTS_CONTROL_TOGGLE:    DWORD = 0x00003000;
+
 // Parity values
 pub const NOPARITY:    BYTE = 0;
 pub const ODDPARITY:   BYTE = 1;
@@ -135,6 +167,17 @@ pub const MS_DSR_ON:  DWORD = 0x0020;
 pub const MS_RING_ON: DWORD = 0x0040;
 pub const MS_RLSD_ON: DWORD = 0x0080;
 
+// Event masks
+pub const EV_RXCHAR:   DWORD = 0x0001;
+pub const EV_RXFLAG:   DWORD = 0x0002;
+pub const EV_TXEMPTY:  DWORD = 0x0004;
+pub const EV_CTS:      DWORD = 0x0008;
+pub const EV_DSR:      DWORD = 0x0010;
+pub const EV_RLSD:     DWORD = 0x0020;
+pub const EV_BREAK:    DWORD = 0x0040;
+pub const EV_ERR:      DWORD = 0x0080;
+pub const EV_RING:     DWORD = 0x0100;
+
 #[derive(Copy,Clone,Debug)]
 #[repr(C)]
 pub struct COMMTIMEOUTS {
@@ -169,12 +212,50 @@ pub struct LPCOMMPROP {
     pub wcProvChar: [WCHAR;1],
 }
 
+// COMMPROP dwMaxBaud and dwSettableBaud values
+pub const BAUD_075:    DWORD = 0x00000001;
+pub const BAUD_110:    DWORD = 0x00000002;
+pub const BAUD_134_5:  DWORD = 0x00000004;
+pub const BAUD_150:    DWORD = 0x00000008;
+pub const BAUD_300:    DWORD = 0x00000010;
+pub const BAUD_600:    DWORD = 0x00000020;
+pub const BAUD_1200:   DWORD = 0x00000040;
+pub const BAUD_1800:   DWORD = 0x00000080;
+pub const BAUD_2400:   DWORD = 0x00000100;
+pub const BAUD_4800:   DWORD = 0x00000200;
+pub const BAUD_7200:   DWORD = 0x00000400;
+pub const BAUD_9600:   DWORD = 0x00000800;
+pub const BAUD_14400:  DWORD = 0x00001000;
+pub const BAUD_19200:  DWORD = 0x00002000;
+pub const BAUD_38400:  DWORD = 0x00004000;
+pub const BAUD_56K:    DWORD = 0x00008000;
+pub const BAUD_128K:   DWORD = 0x00010000;
+pub const BAUD_115200: DWORD = 0x00020000;
+pub const BAUD_57600:  DWORD = 0x00040000;
+pub const BAUD_USER:   DWORD = 0x10000000;
+
+// COMMPROP dwSettableParams values
+pub const SP_PARITY:       DWORD = 0x0001;
+pub const SP_BAUD:         DWORD = 0x0002;
+pub const SP_DATABITS:     DWORD = 0x0004;
+pub const SP_STOPBITS:     DWORD = 0x0008;
+pub const SP_HANDSHAKING:  DWORD = 0x0010;
+pub const SP_PARITY_CHECK: DWORD = 0x0020;
+pub const SP_RLSD:         DWORD = 0x0040;
+
 //Purge flags: https://msdn.microsoft.com/en-us/library/windows/desktop/aa363428%28v=vs.85%29.aspx
 pub const PURGE_RXABORT: DWORD = 0x0002;
 pub const PURGE_RXCLEAR: DWORD = 0x0008;
 pub const PURGE_TXABORT: DWORD = 0x0001;
 pub const PURGE_TXCLEAR: DWORD = 0x0004;
 
+//ClearCommError error flags: https://msdn.microsoft.com/en-us/library/windows/desktop/aa363180%28v=vs.85%29.aspx
+pub const CE_RXOVER:   DWORD = 0x0001;
+pub const CE_OVERRUN:  DWORD = 0x0002;
+pub const CE_RXPARITY: DWORD = 0x0004;
+pub const CE_FRAME:    DWORD = 0x0008;
+pub const CE_BREAK:    DWORD = 0x0010;
+
 //COMSTAT structure: https://msdn.microsoft.com/en-us/library/windows/desktop/aa363200%28v=vs.85%29.aspx
 #[derive(Copy,Clone,Debug,Default)]
 #[repr(C)]
@@ -191,6 +272,72 @@ pub struct COMSTAT {
     pub cbOutQue: DWORD,
 }
 
+//GUID structure: https://msdn.microsoft.com/en-us/library/windows/desktop/aa373931%28v=vs.85%29.aspx
+#[derive(Copy,Clone,Debug)]
+#[repr(C)]
+pub struct GUID {
+    pub Data1: DWORD,
+    pub Data2: WORD,
+    pub Data3: WORD,
+    pub Data4: [BYTE;8],
+}
+
+// Device setup class for serial and parallel ports
+pub const GUID_DEVCLASS_PORTS: GUID = GUID {
+    Data1: 0x4D36E978,
+    Data2: 0xE325,
+    Data3: 0x11CE,
+    Data4: [0xBF, 0xC1, 0x08, 0x00, 0x2B, 0xE1, 0x03, 0x18],
+};
+
+//SP_DEVINFO_DATA structure: https://msdn.microsoft.com/en-us/library/windows/hardware/ff552344%28v=vs.85%29.aspx
+#[derive(Copy,Clone,Debug)]
+#[repr(C)]
+pub struct SP_DEVINFO_DATA {
+    pub cbSize: DWORD,
+    pub ClassGuid: GUID,
+    pub DevInst: DWORD,
+    pub Reserved: usize,
+}
+
+impl SP_DEVINFO_DATA {
+    pub fn new() -> Self {
+        let mut data: SP_DEVINFO_DATA = unsafe { mem::zeroed() };
+        data.cbSize = mem::size_of_val(&data) as DWORD;
+        data
+    }
+}
+
+impl Default for SP_DEVINFO_DATA {
+    fn default() -> Self {
+        SP_DEVINFO_DATA::new()
+    }
+}
+
+// SetupDiGetClassDevs flags
+pub const DIGCF_PRESENT: DWORD = 0x00000002;
+
+// SetupDiOpenDevRegKey values
+pub const DICS_FLAG_GLOBAL: DWORD = 0x00000001;
+pub const DIREG_DEV:        DWORD = 0x00000001;
+
+// SetupDiGetDeviceRegistryProperty properties
+pub const SPDRP_DEVICEDESC: DWORD = 0x00000000;
+pub const SPDRP_MFG:        DWORD = 0x0000000B;
+
+// Registry values
+pub const KEY_READ: DWORD = 0x00020019;
+pub const REG_SZ:   DWORD = 1;
+
+// System error codes
+pub const ERROR_SUCCESS:        LONG = 0;
+pub const ERROR_INVALID_HANDLE: DWORD = 6;
+pub const ERROR_INSUFFICIENT_BUFFER: DWORD = 122;
+pub const ERROR_NO_MORE_ITEMS:  DWORD = 259;
+pub const ERROR_OPERATION_ABORTED: DWORD = 995;
+pub const ERROR_IO_INCOMPLETE:  DWORD = 996;
+pub const ERROR_IO_PENDING:     DWORD = 997;
+
 extern "system" {
     pub fn CreateFileW(lpFileName: LPCWSTR,
                        dwDesiredAccess: DWORD,
@@ -211,6 +358,35 @@ extern "system" {
                      lpNumberOfBytesWritten: LPDWORD,
                      lpOverlapped: LPOVERLAPPED) -> BOOL;
     pub fn FlushFileBuffers(hFile: HANDLE) -> BOOL;
+    pub fn GetOverlappedResult(hFile: HANDLE,
+                               lpOverlapped: LPOVERLAPPED,
+                               lpNumberOfBytesTransferred: LPDWORD,
+                               bWait: BOOL) -> BOOL;
+    pub fn CancelIoEx(hFile: HANDLE, lpOverlapped: LPOVERLAPPED) -> BOOL;
+    pub fn CreateEventW(lpEventAttributes: LPSECURITY_ATTRIBUTES,
+                        bManualReset: BOOL,
+                        bInitialState: BOOL,
+                        lpName: LPCWSTR) -> HANDLE;
+    pub fn WaitForSingleObject(hHandle: HANDLE, dwMilliseconds: DWORD) -> DWORD;
+    pub fn WaitForMultipleObjects(nCount: DWORD,
+                                  lpHandles: *const HANDLE,
+                                  bWaitAll: BOOL,
+                                  dwMilliseconds: DWORD) -> DWORD;
+    pub fn RegisterWaitForSingleObject(phNewWaitObject: *mut HANDLE,
+                                       hObject: HANDLE,
+                                       Callback: WAITORTIMERCALLBACK,
+                                       Context: LPVOID,
+                                       dwMilliseconds: DWORD,
+                                       dwFlags: DWORD) -> BOOL;
+    pub fn UnregisterWaitEx(WaitHandle: HANDLE, CompletionEvent: HANDLE) -> BOOL;
+    pub fn GetCurrentProcess() -> HANDLE;
+    pub fn DuplicateHandle(hSourceProcessHandle: HANDLE,
+                           hSourceHandle: HANDLE,
+                           hTargetProcessHandle: HANDLE,
+                           lpTargetHandle: *mut HANDLE,
+                           dwDesiredAccess: DWORD,
+                           bInheritHandle: BOOL,
+                           dwOptions: DWORD) -> BOOL;
 
     pub fn GetCommState(hFile: HANDLE, lpDCB: *mut DCB) -> BOOL;
     pub fn SetCommState(hFile: HANDLE, lpDCB: *const DCB) -> BOOL;
@@ -218,10 +394,56 @@ extern "system" {
     pub fn SetCommTimeouts(hFile: HANDLE, lpCommTimeouts: *const COMMTIMEOUTS) -> BOOL;
     pub fn EscapeCommFunction(hFile: HANDLE, dwFunc: DWORD) -> BOOL;
     pub fn GetCommModemStatus(hFile: HANDLE, lpModemStat: *mut DWORD) -> BOOL;
+    pub fn GetCommMask(hFile: HANDLE, lpEvtMask: LPDWORD) -> BOOL;
+    pub fn SetCommMask(hFile: HANDLE, dwEvtMask: DWORD) -> BOOL;
+    pub fn WaitCommEvent(hFile: HANDLE, lpEvtMask: LPDWORD, lpOverlapped: LPOVERLAPPED) -> BOOL;
 
     pub fn GetLastError() -> DWORD;
+    pub fn QueryDosDeviceW(lpDeviceName: LPCWSTR, lpTargetPath: LPWSTR, ucchMax: DWORD) -> DWORD;
 
     pub fn GetCommProperties(hFile: HANDLE, lpCommProp: *mut LPCOMMPROP ) -> BOOL;
     pub fn PurgeComm(hFile: HANDLE, dwFlags: DWORD ) -> BOOL;
+    pub fn SetupComm(hFile: HANDLE, dwInQueue: DWORD, dwOutQueue: DWORD) -> BOOL;
     pub fn ClearCommError(hFile: HANDLE, lpErrors: LPDWORD, lpStat: *mut COMSTAT ) -> BOOL;
 }
+
+#[link(name = "advapi32")]
+extern "system" {
+    pub fn RegQueryValueExW(hKey: HKEY,
+                            lpValueName: LPCWSTR,
+                            lpReserved: LPDWORD,
+                            lpType: LPDWORD,
+                            lpData: *mut BYTE,
+                            lpcbData: LPDWORD) -> LONG;
+    pub fn RegCloseKey(hKey: HKEY) -> LONG;
+}
+
+#[link(name = "setupapi")]
+extern "system" {
+    pub fn SetupDiGetClassDevsW(ClassGuid: *const GUID,
+                                Enumerator: LPCWSTR,
+                                hwndParent: HANDLE,
+                                Flags: DWORD) -> HDEVINFO;
+    pub fn SetupDiEnumDeviceInfo(DeviceInfoSet: HDEVINFO,
+                                 MemberIndex: DWORD,
+                                 DeviceInfoData: *mut SP_DEVINFO_DATA) -> BOOL;
+    pub fn SetupDiGetDeviceInstanceIdW(DeviceInfoSet: HDEVINFO,
+                                       DeviceInfoData: *mut SP_DEVINFO_DATA,
+                                       DeviceInstanceId: LPWSTR,
+                                       DeviceInstanceIdSize: DWORD,
+                                       RequiredSize: LPDWORD) -> BOOL;
+    pub fn SetupDiOpenDevRegKey(DeviceInfoSet: HDEVINFO,
+                                DeviceInfoData: *mut SP_DEVINFO_DATA,
+                                Scope: DWORD,
+                                HwProfile: DWORD,
+                                KeyType: DWORD,
+                                samDesired: DWORD) -> HKEY;
+    pub fn SetupDiGetDeviceRegistryPropertyW(DeviceInfoSet: HDEVINFO,
+                                             DeviceInfoData: *mut SP_DEVINFO_DATA,
+                                             Property: DWORD,
+                                             PropertyRegDataType: LPDWORD,
+                                             PropertyBuffer: *mut BYTE,
+                                             PropertyBufferSize: DWORD,
+                                             RequiredSize: LPDWORD) -> BOOL;
+    pub fn SetupDiDestroyDeviceInfoList(DeviceInfoSet: HDEVINFO) -> BOOL;
+}
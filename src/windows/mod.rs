@@ -1,5 +1,17 @@
 pub use self::com::*;
+pub use self::enumerate::{available_ports,ports,port_exists,Ports};
+pub use self::poll::PortSet;
 
+pub(crate) use self::error::is_disconnect_error;
+
+#[cfg(feature = "async")]
+pub use self::async_com::AsyncCOMPort;
+
+#[cfg(feature = "async")]
+mod async_com;
 mod com;
+mod enumerate;
 mod error;
+mod overlapped;
+mod poll;
 pub mod ffi;
@@ -0,0 +1,111 @@
+use std::mem;
+use std::ptr;
+
+use super::ffi::*;
+
+
+/// An `OVERLAPPED` structure with its own event, for one I/O operation at a time.
+///
+/// A handle that was opened for overlapped I/O needs an `OVERLAPPED` structure for every read,
+/// write, and wait, and the structure must stay in place until the operation has completed.
+/// `finish()` doesn't return while the operation is still pending, so an `Overlapped` that lives
+/// on the stack of the function that started the operation is never freed too early.
+pub struct Overlapped {
+    inner: OVERLAPPED
+}
+
+impl Overlapped {
+    pub fn new() -> ::Result<Self> {
+        // GetOverlappedResult requires a manual-reset event
+        let event = unsafe { CreateEventW(ptr::null_mut(), 1, 0, ptr::null()) };
+
+        if event.is_null() {
+            return Err(super::error::last_os_error());
+        }
+
+        let mut inner: OVERLAPPED = unsafe { mem::zeroed() };
+        inner.hEvent = event;
+
+        Ok(Overlapped { inner: inner })
+    }
+
+    /// Returns the event that is signaled when the operation completes.
+    pub fn event(&self) -> HANDLE {
+        self.inner.hEvent
+    }
+
+    pub fn as_mut_ptr(&mut self) -> LPOVERLAPPED {
+        &mut self.inner
+    }
+
+    /// Waits for the operation that was started on `handle` with this `Overlapped` to complete.
+    ///
+    /// `started` is the value returned by the call that started the operation. An operation that
+    /// is still pending after `timeout` milliseconds is cancelled; a timeout of `None` waits until
+    /// the operation completes. Returns the number of bytes transferred, or `None` if the
+    /// operation was cancelled. Errors are created by `error` from the thread's last error code.
+    ///
+    /// Handles that weren't opened for overlapped I/O complete every operation before the call
+    /// that started it returns, so the timeout has no effect on them.
+    pub fn finish<E>(&mut self, handle: HANDLE, started: BOOL, timeout: Option<DWORD>, error: fn() -> E) -> Result<Option<DWORD>, E> {
+        if started == 0 {
+            if unsafe { GetLastError() } != ERROR_IO_PENDING {
+                return Err(error());
+            }
+
+            if let Some(timeout) = timeout {
+                if unsafe { WaitForSingleObject(self.inner.hEvent, timeout) } != WAIT_OBJECT_0 {
+                    // an operation that completes before it's cancelled keeps its result
+                    unsafe { CancelIoEx(handle, &mut self.inner) };
+                }
+            }
+        }
+
+        let mut transferred: DWORD = 0;
+
+        // cancelled or not, the operation must be done before the OVERLAPPED can go away
+        match unsafe { GetOverlappedResult(handle, &mut self.inner, &mut transferred, 1) } {
+            0 if unsafe { GetLastError() } == ERROR_OPERATION_ABORTED => Ok(None),
+            0 => Err(error()),
+            _ => Ok(Some(transferred))
+        }
+    }
+
+    /// Returns the result of an operation that was started on `handle` without waiting for it.
+    ///
+    /// Returns `None` while the operation is still pending. Otherwise, the result is the same as
+    /// that of `finish()`.
+    #[cfg(feature = "async")]
+    pub fn try_finish<E>(&mut self, handle: HANDLE, error: fn() -> E) -> Option<Result<Option<DWORD>, E>> {
+        let mut transferred: DWORD = 0;
+
+        match unsafe { GetOverlappedResult(handle, &mut self.inner, &mut transferred, 0) } {
+            0 => match unsafe { GetLastError() } {
+                ERROR_IO_INCOMPLETE => None,
+                ERROR_OPERATION_ABORTED => Some(Ok(None)),
+                _ => Some(Err(error()))
+            },
+            _ => Some(Ok(Some(transferred)))
+        }
+    }
+
+    /// Cancels an operation that was started on `handle` and waits until it's done.
+    ///
+    /// An operation that has already completed keeps its result.
+    pub fn cancel(&mut self, handle: HANDLE) {
+        let mut transferred: DWORD = 0;
+
+        unsafe {
+            CancelIoEx(handle, &mut self.inner);
+            GetOverlappedResult(handle, &mut self.inner, &mut transferred, 1);
+        }
+    }
+}
+
+impl Drop for Overlapped {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.inner.hEvent);
+        }
+    }
+}
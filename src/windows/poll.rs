@@ -0,0 +1,202 @@
+use std::thread;
+use std::time::{Duration,Instant};
+
+use std::os::windows::prelude::*;
+
+use super::ffi::*;
+use super::com::{COMPort,duration_to_millis};
+use super::overlapped::Overlapped;
+
+
+/// A set of COM ports that can be waited on together.
+///
+/// `PortSet` lets a single thread watch many ports for received data. Ports are registered with
+/// `add()`, which returns the index that `wait()` uses to report the port. The set only holds
+/// shared references, and `&COMPort` implements `Read`, so the ports can be read while they're
+/// registered.
+///
+/// If every port in the set was opened with `OpenOptions::overlapped()`, `wait()` waits for the
+/// ports' `EV_RXCHAR` events with `WaitForMultipleObjects`, for up to 64 ports. While it waits, the
+/// event mask of every port is replaced, which ends a `wait_event()` that is in progress on another
+/// thread. Windows can't cancel a wait for an event on other ports, so `wait()` polls the driver's
+/// receive queue of every port about once per millisecond instead. Either way, line errors that
+/// are found while checking the receive queues are held for `line_errors()`.
+///
+/// ```no_run
+/// use std::io::prelude::*;
+/// use std::time::Duration;
+/// use serial::windows::{COMPort,PortSet};
+///
+/// let ports = vec![COMPort::open("COM1").unwrap(), COMPort::open("COM2").unwrap()];
+///
+/// let mut set = PortSet::new();
+///
+/// for port in &ports {
+///     set.add(port);
+/// }
+///
+/// for index in set.wait(Duration::from_secs(1)).unwrap() {
+///     let mut buf = [0u8; 64];
+///     let n = (&ports[index]).read(&mut buf).unwrap();
+///     println!("{}: {:?}", ports[index].name(), &buf[..n]);
+/// }
+/// ```
+#[derive(Debug,Default)]
+pub struct PortSet<'a> {
+    ports: Vec<&'a COMPort>
+}
+
+impl<'a> PortSet<'a> {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        PortSet { ports: Vec::new() }
+    }
+
+    /// Adds a port to the set and returns its index.
+    ///
+    /// Indexes are assigned in the order that ports are added, starting at zero.
+    pub fn add(&mut self, port: &'a COMPort) -> usize {
+        self.ports.push(port);
+        self.ports.len() - 1
+    }
+
+    /// Returns the number of ports in the set.
+    pub fn len(&self) -> usize {
+        self.ports.len()
+    }
+
+    /// Returns `true` if the set contains no ports.
+    pub fn is_empty(&self) -> bool {
+        self.ports.is_empty()
+    }
+
+    /// Blocks until at least one port in the set has received data.
+    ///
+    /// Returns the indexes of all ports that have data to read, in ascending order, or an empty
+    /// `Vec` if none received data before `timeout` expired. A timeout of zero checks the ports
+    /// once without waiting.
+    ///
+    /// ## Errors
+    ///
+    /// * `Disconnected` if one of the devices was disconnected.
+    /// * `Io` for any other type of I/O error.
+    pub fn wait(&mut self, timeout: Duration) -> ::Result<Vec<usize>> {
+        let waitable = !self.ports.is_empty()
+            && self.ports.len() <= MAXIMUM_WAIT_OBJECTS as usize
+            && self.ports.iter().all(|port| port.is_overlapped());
+
+        if waitable && timeout > Duration::from_millis(0) {
+            try!(self.wait_for_events(timeout));
+            return self.readable();
+        }
+
+        let start = Instant::now();
+
+        loop {
+            let readable = try!(self.readable());
+
+            if !readable.is_empty() || start.elapsed() >= timeout {
+                return Ok(readable);
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn readable(&self) -> ::Result<Vec<usize>> {
+        let mut readable = Vec::new();
+
+        for (index, port) in self.ports.iter().enumerate() {
+            if try!(port.clear_comm_error()).cbInQue > 0 {
+                readable.push(index);
+            }
+        }
+
+        Ok(readable)
+    }
+
+    // selects EV_RXCHAR on every port for the duration of the wait
+    fn wait_for_events(&self, timeout: Duration) -> ::Result<()> {
+        let mut masks = Vec::with_capacity(self.ports.len());
+        let mut result = Ok(());
+
+        for port in &self.ports {
+            let mut mask: DWORD = 0;
+
+            if unsafe { GetCommMask(handle(port), &mut mask) } == 0 || unsafe { SetCommMask(handle(port), EV_RXCHAR) } == 0 {
+                result = Err(super::error::last_device_error());
+                break;
+            }
+
+            masks.push(mask);
+        }
+
+        if result.is_ok() {
+            result = self.wait_for_rx_char(timeout);
+        }
+
+        for (port, mask) in self.ports.iter().zip(masks) {
+            unsafe { SetCommMask(handle(port), mask) };
+        }
+
+        result
+    }
+
+    fn wait_for_rx_char(&self, timeout: Duration) -> ::Result<()> {
+        // the event masks and OVERLAPPED structures mustn't move while the waits are in progress
+        let mut events: Vec<DWORD> = vec![0; self.ports.len()];
+        let mut overlapped = try!(self.ports.iter().map(|_| Overlapped::new()).collect::<::Result<Vec<_>>>());
+        let mut started = 0;
+        let mut result = Ok(());
+
+        for (port, (overlapped, event)) in self.ports.iter().zip(overlapped.iter_mut().zip(events.iter_mut())) {
+            let pending = unsafe { WaitCommEvent(handle(port), event, overlapped.as_mut_ptr()) };
+
+            if pending == 0 && unsafe { GetLastError() } != ERROR_IO_PENDING {
+                result = Err(super::error::last_device_error());
+                break;
+            }
+
+            started += 1;
+        }
+
+        if result.is_ok() {
+            // EV_RXCHAR only reports bytes that arrive after the mask is set, so check the queues too
+            result = self.readable().and_then(|readable| {
+                if !readable.is_empty() {
+                    return Ok(());
+                }
+
+                let handles: Vec<HANDLE> = overlapped.iter().map(Overlapped::event).collect();
+
+                match unsafe { WaitForMultipleObjects(handles.len() as DWORD, handles.as_ptr(), 0, duration_to_millis(timeout)) } {
+                    WAIT_FAILED => Err(super::error::last_os_error()),
+                    _ => Ok(())
+                }
+            });
+        }
+
+        // every wait must be done before its event mask and OVERLAPPED go away
+        for (port, overlapped) in self.ports.iter().zip(overlapped.iter_mut()).take(started) {
+            overlapped.cancel(handle(port));
+        }
+
+        result
+    }
+}
+
+fn handle(port: &COMPort) -> HANDLE {
+    port.as_raw_handle() as HANDLE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_port_set_reports_no_readable_ports() {
+        let mut set = PortSet::new();
+        assert!(set.is_empty());
+        assert_eq!(set.wait(Duration::from_millis(1)).unwrap(), Vec::<usize>::new());
+    }
+}